@@ -0,0 +1,122 @@
+//! Serializes JSON in the canonical form of [RFC 8785], the JSON
+//! Canonicalization Scheme (JCS), so equal documents hash to the same bytes.
+//!
+//! Object keys are sorted by their UTF-16 code units, numbers are written the
+//! way ECMAScript writes them, strings only escape what JSON requires, and
+//! there is no whitespace. Numbers outside the range of a double and strings
+//! with lone surrogates can't be canonicalized, and are errors.
+//!
+//! [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+use crate::{
+    errors::{ErrorCode, Issue},
+    nodes::{JSONFile, JSONNode, JSONString},
+    strings::{has_lone_surrogate, quote},
+};
+
+/// Returns the canonical form of the file, or every value that has none.
+pub fn to_canonical(file: &JSONFile) -> Result<String, Vec<Issue>> {
+    let mut output = String::new();
+    let mut errors = vec![];
+    write_node(&file.value, &mut output, &mut errors);
+    match errors.is_empty() {
+        true => Ok(output),
+        false => Err(errors),
+    }
+}
+
+fn write_node(node: &JSONNode, output: &mut String, errors: &mut Vec<Issue>) {
+    match node {
+        JSONNode::Null(_) => output.push_str("null"),
+        JSONNode::Bool(bool) => output.push_str(if bool.value { "true" } else { "false" }),
+        JSONNode::Number(number) => {
+            if !number.value.is_finite() {
+                errors.push(Issue::new(
+                    number.span.clone(),
+                    ErrorCode::NumberOutOfRange,
+                    format!("Number {} is too large for a double", number.raw),
+                ));
+            } else {
+                output.push_str(&es6_number(number.value));
+            }
+        }
+        JSONNode::String(string) => write_string(string, output, errors),
+        JSONNode::Array(array) => {
+            output.push('[');
+            for (index, item) in array.items.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_node(item, output, errors);
+            }
+            output.push(']');
+        }
+        JSONNode::Object(object) => {
+            let mut members: Vec<_> = object.members.iter().collect();
+            members.sort_by(|a, b| a.key.value.encode_utf16().cmp(b.key.value.encode_utf16()));
+            output.push('{');
+            for (index, member) in members.into_iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_string(&member.key, output, errors);
+                output.push(':');
+                write_node(&member.value, output, errors);
+            }
+            output.push('}');
+        }
+    }
+}
+
+fn write_string(string: &JSONString, output: &mut String, errors: &mut Vec<Issue>) {
+    if has_lone_surrogate(&string.raw) {
+        errors.push(Issue::new(
+            string.span.clone(),
+            ErrorCode::LoneSurrogate,
+            "String contains a lone surrogate".to_string(),
+        ));
+    }
+    output.push_str(&quote(&string.value));
+}
+
+/// Writes a finite number the way ECMAScript's `Number.prototype.toString`
+/// does, using the shortest digits that round-trip.
+pub fn es6_number(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if value < 0.0 { "-" } else { "" };
+    // Rust also picks the shortest round-tripping digits, e.g. `1.25e-7`.
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("Scientific notation should have an exponent");
+    let digits = mantissa.replace('.', "");
+    let exponent: i32 = exponent.parse().expect("Exponent should be an integer");
+    // the decimal point goes after `point` digits.
+    let point = exponent + 1;
+    let length = digits.len() as i32;
+
+    let number = if length <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - length) as usize))
+    } else if 0 < point && point <= 21 {
+        let (whole, fraction) = digits.split_at(point as usize);
+        format!("{}.{}", whole, fraction)
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let fraction = match rest {
+            "" => String::new(),
+            rest => format!(".{}", rest),
+        };
+        let exponent_sign = if point > 0 { "+" } else { "-" };
+        format!(
+            "{}{}e{}{}",
+            first,
+            fraction,
+            exponent_sign,
+            (point - 1).abs()
+        )
+    };
+    format!("{}{}", sign, number)
+}
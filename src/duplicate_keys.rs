@@ -0,0 +1,192 @@
+//! Finds objects that define the same key more than once.
+//!
+//! Keys are compared after unescaping, so `"a"` and `"\u0061"` are duplicates.
+use std::{collections::HashMap, ops::Range, str::FromStr};
+
+use crate::{
+    errors::{ErrorCode, Issue},
+    nodes::{JSONFile, JSONNode, JSONObject},
+    strings,
+    tokenizer::{Token, TokenType},
+};
+
+/// How to handle duplicate keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Keep every member, without reporting anything.
+    Allow,
+    /// Keep every member, and report each duplicate as a warning.
+    #[default]
+    Warn,
+    /// Report each duplicate as an error, and fail.
+    Error,
+    /// Keep the first member with each key, and drop the rest with a warning.
+    KeepFirst,
+    /// Keep the last member with each key, and drop the rest with a warning.
+    KeepLast,
+}
+
+impl FromStr for DuplicateKeyPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "allow" => Ok(DuplicateKeyPolicy::Allow),
+            "warn" => Ok(DuplicateKeyPolicy::Warn),
+            "error" => Ok(DuplicateKeyPolicy::Error),
+            "keep-first" => Ok(DuplicateKeyPolicy::KeepFirst),
+            "keep-last" => Ok(DuplicateKeyPolicy::KeepLast),
+            _ => Err(format!(
+                "Invalid duplicate key policy {:?}, expected allow, warn, error, keep-first or keep-last",
+                value
+            )),
+        }
+    }
+}
+
+/// A key defined again in the same object.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    /// The unescaped key.
+    pub key: String,
+    /// Range of the repeated key in the source.
+    pub span: Range<usize>,
+    /// Range of the first definition of the key in the source.
+    pub first_span: Range<usize>,
+}
+
+/// Finds the duplicate keys in every object of the file, in source order.
+///
+/// With [DuplicateKeyPolicy::KeepFirst] or [DuplicateKeyPolicy::KeepLast],
+/// the members that lose are removed from the tree.
+pub fn check(file: &mut JSONFile, policy: DuplicateKeyPolicy) -> Vec<DuplicateKey> {
+    let mut duplicates = vec![];
+    if policy != DuplicateKeyPolicy::Allow {
+        check_node(&mut file.value, policy, &mut duplicates);
+    }
+    duplicates.sort_by_key(|duplicate| duplicate.span.start);
+    duplicates
+}
+
+/// Finds the duplicate keys in every object, in source order, from the
+/// validated tokens alone.
+///
+/// This is enough to report duplicates without building a tree, but not to
+/// drop them, which is what [check] is for.
+pub fn check_tokens(tokens: &[Token]) -> Vec<DuplicateKey> {
+    let mut duplicates = vec![];
+    // the first definition of each key, for every open object; arrays have none.
+    let mut containers: Vec<Option<HashMap<String, Range<usize>>>> = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        match token.token_type {
+            TokenType::LeftBrace => containers.push(Some(HashMap::new())),
+            TokenType::LeftBracket => containers.push(None),
+            TokenType::RightBrace | TokenType::RightBracket => {
+                containers.pop();
+            }
+            TokenType::String(raw) => {
+                let is_key = tokens
+                    .get(index + 1)
+                    .is_some_and(|next| next.token_type == TokenType::Colon);
+                let Some(Some(keys)) = containers.last_mut().filter(|_| is_key) else {
+                    continue;
+                };
+                let key = strings::unquote(raw);
+                match keys.get(&key) {
+                    Some(first_span) => duplicates.push(DuplicateKey {
+                        first_span: first_span.clone(),
+                        span: token.span(),
+                        key,
+                    }),
+                    None => {
+                        keys.insert(key, token.span());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    duplicates
+}
+
+fn check_node(node: &mut JSONNode, policy: DuplicateKeyPolicy, duplicates: &mut Vec<DuplicateKey>) {
+    match node {
+        JSONNode::Array(array) => {
+            for item in &mut array.items {
+                check_node(item, policy, duplicates);
+            }
+        }
+        JSONNode::Object(object) => {
+            check_object(object, policy, duplicates);
+            for member in &mut object.members {
+                check_node(&mut member.value, policy, duplicates);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_object(
+    object: &mut JSONObject,
+    policy: DuplicateKeyPolicy,
+    duplicates: &mut Vec<DuplicateKey>,
+) {
+    // the index of the first member with each key.
+    let mut first_members: HashMap<&str, usize> = HashMap::new();
+    // whether to keep each member.
+    let mut keep = vec![true; object.members.len()];
+    for (index, member) in object.members.iter().enumerate() {
+        let Some(&first) = first_members.get(member.key.value.as_str()) else {
+            first_members.insert(&member.key.value, index);
+            continue;
+        };
+        duplicates.push(DuplicateKey {
+            key: member.key.value.clone(),
+            span: member.key.span.clone(),
+            first_span: object.members[first].key.span.clone(),
+        });
+        match policy {
+            DuplicateKeyPolicy::KeepFirst => keep[index] = false,
+            DuplicateKeyPolicy::KeepLast => {
+                // drop the member kept so far, which is the last one seen.
+                let previous = (0..index)
+                    .rev()
+                    .find(|&i| keep[i] && object.members[i].key.value == member.key.value)
+                    .expect("The first member is kept until now");
+                keep[previous] = false;
+            }
+            _ => {}
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    object.members.retain(|_| keep.next().unwrap_or(true));
+}
+
+impl DuplicateKey {
+    /// Returns the message reported for the duplicate, under the given policy.
+    pub fn message(&self, policy: DuplicateKeyPolicy) -> String {
+        match policy {
+            DuplicateKeyPolicy::KeepFirst => {
+                format!(
+                    "Duplicate key {:?}, dropped in favor of the first one",
+                    self.key
+                )
+            }
+            DuplicateKeyPolicy::KeepLast => {
+                format!("Duplicate key {:?}, the last one is kept", self.key)
+            }
+            _ => format!("Duplicate key {:?}", self.key),
+        }
+    }
+
+    /// Returns the duplicate as an [Issue] pointing at both occurrences.
+    pub fn to_issue(&self, policy: DuplicateKeyPolicy) -> Issue {
+        Issue::new(
+            self.span.clone(),
+            ErrorCode::DuplicateKey,
+            self.message(policy),
+        )
+        .with_related(self.first_span.clone(), "First defined here")
+    }
+}
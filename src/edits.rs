@@ -0,0 +1,289 @@
+//! Formats part of a source as text edits, for editors that format a
+//! selection or apply changes in place.
+//!
+//! Edits are made against the source as given, so they can be applied to an
+//! editor buffer without replacing the rest of it, or moving cursors in
+//! lines that didn't change.
+use std::ops::Range;
+
+use crate::{
+    errors::FormatJsonError,
+    nodes::JSONNode,
+    options::{BlankLines, FormatOptions},
+    parser,
+    token_formatter::{TokenFormatter, INDENT},
+    tokenizer::{self, Token, TokenType},
+    validator,
+};
+
+/// Replaces a byte range of the source with new text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Applies the edits to the source. The edits must not overlap.
+    pub fn apply(edits: &[TextEdit], source: &str) -> String {
+        let mut edits: Vec<_> = edits.iter().collect();
+        edits.sort_by_key(|edit| edit.range.start);
+        let mut output = String::new();
+        let mut offset = 0;
+        for edit in edits {
+            output.push_str(&source[offset..edit.range.start]);
+            output.push_str(&edit.replacement);
+            offset = edit.range.end;
+        }
+        output.push_str(&source[offset..]);
+        output
+    }
+}
+
+/// Formats the whole source, as the contents of the file at `filepath`, and
+/// returns the edits that turn it into the formatted output. Check
+/// [format_json_as_file][crate::format_json_as_file] for how the path is used,
+/// and [diff] for how edits are found.
+///
+/// Throws a [FormatJsonError] on invalid syntax.
+pub fn format_edits(
+    source: &str,
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<Vec<TextEdit>, FormatJsonError> {
+    let formatted = crate::format_json_as_file(source, filepath, options)?;
+    Ok(diff(source, &formatted.output))
+}
+
+/// Returns the edits that turn the source into the formatted output, diffing
+/// them token by token.
+///
+/// When only whitespace changed, which is the case unless options rewrite
+/// strings, numbers or the order of keys, every edit is inside the
+/// whitespace between two tokens. Otherwise the tokens that changed are
+/// replaced by one edit. Edits are trimmed to the text that differs, and are
+/// returned in source order.
+pub fn diff(source: &str, formatted: &str) -> Vec<TextEdit> {
+    let mut edits = vec![];
+    let (Ok(before), Ok(after)) = (
+        tokenizer::tokenize(source, "<string>".to_string()),
+        tokenizer::tokenize(formatted, "<string>".to_string()),
+    ) else {
+        push_edit(&mut edits, 0, source, formatted);
+        return edits;
+    };
+    let same = |(old, new): (&Token, &Token)| source[old.span()] == formatted[new.span()];
+    let prefix = before
+        .iter()
+        .zip(&after)
+        .take_while(|&pair| same(pair))
+        .count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|&pair| same(pair))
+        .count();
+
+    let old_gaps = gaps(&before, source.len());
+    let new_gaps = gaps(&after, formatted.len());
+    let pairs = |old: &[Range<usize>], new: &[Range<usize>]| {
+        old.iter()
+            .cloned()
+            .zip(new.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+    // the ranges to compare, in the source and in the formatted output.
+    let mut changes = pairs(&old_gaps, &new_gaps);
+    if prefix < before.len() || prefix < after.len() {
+        let (old_end, new_end) = (before.len() - suffix, after.len() - suffix);
+        changes = pairs(&old_gaps[..prefix], &new_gaps[..prefix]);
+        // the changed tokens, from the gap before the first to the gap after the last.
+        changes.push((
+            old_gaps[prefix].start..old_gaps[old_end].end,
+            new_gaps[prefix].start..new_gaps[new_end].end,
+        ));
+        changes.extend(pairs(&old_gaps[old_end + 1..], &new_gaps[new_end + 1..]));
+    }
+    for (old, new) in changes {
+        push_edit(&mut edits, old.start, &source[old], &formatted[new]);
+    }
+    edits
+}
+
+/// Returns the range of the whitespace before each token, and after the last.
+fn gaps(tokens: &[Token], len: usize) -> Vec<Range<usize>> {
+    let mut start = 0;
+    let mut gaps = vec![];
+    for token in tokens {
+        gaps.push(start..token.byte_offset);
+        start = token.end_offset;
+    }
+    gaps.push(start..len);
+    gaps
+}
+
+/// Adds an edit replacing `old`, which starts at `offset`, with `new`,
+/// leaving out the start and end they have in common.
+fn push_edit(edits: &mut Vec<TextEdit>, offset: usize, old: &str, new: &str) {
+    let common_len = |old: &mut dyn Iterator<Item = char>, new: &mut dyn Iterator<Item = char>| {
+        old.zip(new)
+            .take_while(|(old, new)| old == new)
+            .map(|(char, _)| char.len_utf8())
+            .sum::<usize>()
+    };
+    let prefix = common_len(&mut old.chars(), &mut new.chars());
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = common_len(&mut old.chars().rev(), &mut new.chars().rev());
+    if old.len() == suffix && new.len() == suffix {
+        return;
+    }
+    edits.push(TextEdit {
+        range: offset + prefix..offset + old.len() - suffix + prefix,
+        replacement: new[..new.len() - suffix].to_string(),
+    });
+}
+
+/// The values to format, and their nesting depth.
+struct Selection {
+    span: Range<usize>,
+    depth: usize,
+}
+
+/// Formats the smallest complete values enclosing the byte range, indented
+/// for their nesting depth.
+///
+/// When the range spans several items of an array or members of an object,
+/// just those are formatted, along with the whitespace before the first one.
+/// The layout is that of [TokenFormatter], and only the line ending and
+/// blank line options are used: options that rewrite or lay out the whole
+/// document are ignored.
+///
+/// Returns no edits when the values are already formatted, and otherwise one
+/// edit, trimmed to the text that differs.
+///
+/// Throws a [FormatJsonError] on invalid syntax anywhere in the source.
+pub fn format_range(
+    source: &str,
+    range: Range<usize>,
+    options: &FormatOptions,
+) -> Result<Vec<TextEdit>, FormatJsonError> {
+    options
+        .limits
+        .check_size(source.len(), "<string>", source)?;
+    let tokens = tokenizer::tokenize_with_limits(source, "<string>".to_string(), &options.limits)?;
+    let errors = validator::validate_with_limits(&tokens, options.max_errors, &options.limits);
+    if let Some(diagnostic) = validator::errors_to_diagnostic(&errors, "<string>", source, &tokens)
+    {
+        return Err(diagnostic.into());
+    }
+    let tree = parser::from_tokens(&tokens);
+
+    let start = range.start.min(source.len());
+    let range = start..range.end.clamp(start, source.len());
+    let selection = select(&tree.value, &range, 0);
+    let first = tokens.partition_point(|token| token.byte_offset < selection.span.start);
+    let last = tokens.partition_point(|token| token.end_offset <= selection.span.end);
+    let selected = &tokens[first..last];
+
+    let newline = options.line_ending.newline(source);
+    // rewrite the whitespace before the values too, unless they start the file.
+    let (start, mut replacement) = match first.checked_sub(1).map(|index| &tokens[index]) {
+        Some(previous) => (
+            previous.end_offset,
+            separator(previous, &selected[0], selection.depth, newline, options),
+        ),
+        None => (selection.span.start, String::new()),
+    };
+    let formatter = TokenFormatter::with_options(selected.iter().cloned(), source, options)
+        .with_indent_level(selection.depth);
+    replacement.extend(formatter);
+
+    let mut edits = vec![];
+    push_edit(
+        &mut edits,
+        start,
+        &source[start..selection.span.end],
+        &replacement,
+    );
+    Ok(edits)
+}
+
+/// Finds the smallest values that enclose the range, where `node` is at
+/// nesting `depth`.
+fn select(node: &JSONNode, range: &Range<usize>, depth: usize) -> Selection {
+    let whole = Selection {
+        span: node.span(),
+        depth,
+    };
+    // the span of each child, and the child to descend into if any.
+    let children: Vec<(Range<usize>, Option<&JSONNode>)> = match node {
+        JSONNode::Array(array) => array
+            .items
+            .iter()
+            .map(|item| (item.span(), Some(item)))
+            .collect(),
+        JSONNode::Object(object) => object
+            .members
+            .iter()
+            .map(|member| {
+                let value = member.value.span();
+                let descend = value.start <= range.start && range.end <= value.end;
+                (
+                    member.key.span.start..value.end,
+                    descend.then_some(&member.value),
+                )
+            })
+            .collect(),
+        _ => return whole,
+    };
+    // ranges touching the brackets select the whole container.
+    let span = node.span();
+    if range.start <= span.start || range.end >= span.end {
+        return whole;
+    }
+
+    let overlapping: Vec<_> = children
+        .iter()
+        .filter(|(child, _)| match range.is_empty() {
+            true => child.start <= range.start && range.start <= child.end,
+            false => range.start < child.end && child.start < range.end,
+        })
+        .collect();
+    match overlapping[..] {
+        [] => whole,
+        [(child, Some(descend))] if child.start <= range.start && range.end <= child.end => {
+            select(descend, range, depth + 1)
+        }
+        [(child, _)] => Selection {
+            span: child.clone(),
+            depth: depth + 1,
+        },
+        [(first, _), .., (last, _)] => Selection {
+            span: first.start..last.end,
+            depth: depth + 1,
+        },
+    }
+}
+
+/// Returns the whitespace the formatter writes between the two tokens.
+fn separator(
+    previous: &Token,
+    next: &Token,
+    depth: usize,
+    newline: &str,
+    options: &FormatOptions,
+) -> String {
+    match previous.token_type {
+        TokenType::Colon => " ".to_string(),
+        TokenType::Comma | TokenType::LeftBracket | TokenType::LeftBrace => {
+            let blank_line = previous.token_type == TokenType::Comma
+                && options.blank_lines == BlankLines::Preserve
+                && next.blank_line_before;
+            let mut separator = newline.repeat(if blank_line { 2 } else { 1 });
+            separator.push_str(&INDENT.repeat(depth));
+            separator
+        }
+        _ => String::new(),
+    }
+}
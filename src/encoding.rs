@@ -0,0 +1,231 @@
+//! Detects and decodes the Unicode encoding of JSON files.
+//!
+//! JSON text may be encoded in UTF-8, UTF-16 or UTF-32. The encoding is detected
+//! using the byte order mark if present, or otherwise from the pattern of null
+//! bytes in the first four bytes, as described in RFC 4627 section 3 (and
+//! referenced by RFC 8259).
+use std::{fmt::Display, str::FromStr};
+
+use crate::errors::{FormatJsonError, InvalidSyntaxDiagnostic};
+
+/// The Unicode encodings a JSON file can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        })
+    }
+}
+
+impl Encoding {
+    /// Detects the encoding of the given bytes.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        // Byte order marks come first. UTF-32LE must be checked before
+        // UTF-16LE, as its byte order mark starts with the UTF-16LE one.
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => return Encoding::Utf8,
+            [0xFF, 0xFE, 0x00, 0x00, ..] => return Encoding::Utf32Le,
+            [0x00, 0x00, 0xFE, 0xFF, ..] => return Encoding::Utf32Be,
+            [0xFF, 0xFE, ..] => return Encoding::Utf16Le,
+            [0xFE, 0xFF, ..] => return Encoding::Utf16Be,
+            _ => {}
+        }
+        // The first two characters of a JSON text are always ASCII, so the
+        // position of null bytes gives away the encoding.
+        match bytes {
+            [0, 0, 0, _, ..] => Encoding::Utf32Be,
+            [_, 0, 0, 0, ..] => Encoding::Utf32Le,
+            [0, _, ..] => Encoding::Utf16Be,
+            [_, 0, ..] => Encoding::Utf16Le,
+            _ => Encoding::Utf8,
+        }
+    }
+
+    /// Returns the size of a code unit of this encoding, in bytes.
+    fn code_unit_size(&self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+
+    /// Encodes the given text in this encoding.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len() * self.code_unit_size());
+        match self {
+            Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+            Encoding::Utf16Le => text
+                .encode_utf16()
+                .for_each(|unit| bytes.extend(unit.to_le_bytes())),
+            Encoding::Utf16Be => text
+                .encode_utf16()
+                .for_each(|unit| bytes.extend(unit.to_be_bytes())),
+            Encoding::Utf32Le => text
+                .chars()
+                .for_each(|char| bytes.extend((char as u32).to_le_bytes())),
+            Encoding::Utf32Be => text
+                .chars()
+                .for_each(|char| bytes.extend((char as u32).to_be_bytes())),
+        }
+        bytes
+    }
+}
+
+/// Which encoding the formatter writes its output in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncodingPolicy {
+    /// Always write UTF-8.
+    #[default]
+    Utf8,
+    /// Write the output in the encoding the source was read in.
+    Preserve,
+}
+
+impl FromStr for EncodingPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "utf8" | "utf-8" => Ok(EncodingPolicy::Utf8),
+            "preserve" => Ok(EncodingPolicy::Preserve),
+            _ => Err(format!(
+                "Invalid encoding {:?}, expected utf8 or preserve",
+                value
+            )),
+        }
+    }
+}
+
+/// Source text decoded to UTF-8, which remembers where it came from.
+#[derive(Debug)]
+pub struct DecodedSource {
+    pub text: String,
+    pub encoding: Encoding,
+    /// Pairs of (offset in `text`, offset in the original bytes) for every
+    /// character. Empty for UTF-8, where the offsets are the same.
+    offsets: Vec<(usize, usize)>,
+    original_len: usize,
+}
+
+impl DecodedSource {
+    /// Decodes the given bytes, detecting their encoding.
+    ///
+    /// Throws a [FormatJsonError::InvalidEncoding] if the bytes are not valid
+    /// in the detected encoding.
+    pub fn decode(bytes: &[u8]) -> Result<Self, FormatJsonError> {
+        let encoding = Encoding::detect(bytes);
+        if encoding == Encoding::Utf8 {
+            let text = String::from_utf8(bytes.to_vec()).map_err(|err| {
+                FormatJsonError::InvalidEncoding(format!(
+                    "invalid UTF-8 at byte offset {}",
+                    err.utf8_error().valid_up_to()
+                ))
+            })?;
+            return Ok(Self {
+                text,
+                encoding,
+                offsets: vec![],
+                original_len: bytes.len(),
+            });
+        }
+
+        let unit_size = encoding.code_unit_size();
+        if !bytes.len().is_multiple_of(unit_size) {
+            return Err(FormatJsonError::InvalidEncoding(format!(
+                "{} input has a truncated code unit at the end",
+                encoding
+            )));
+        }
+        let units = bytes.chunks_exact(unit_size).map(|chunk| match encoding {
+            Encoding::Utf16Le => u16::from_le_bytes([chunk[0], chunk[1]]) as u32,
+            Encoding::Utf16Be => u16::from_be_bytes([chunk[0], chunk[1]]) as u32,
+            Encoding::Utf32Le => u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+            Encoding::Utf32Be => u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+            Encoding::Utf8 => unreachable!("UTF-8 is decoded above"),
+        });
+
+        let mut text = String::with_capacity(bytes.len() / unit_size);
+        let mut offsets = Vec::with_capacity(bytes.len() / unit_size);
+        let invalid = |original_offset: usize| {
+            FormatJsonError::InvalidEncoding(format!(
+                "invalid {} at byte offset {}",
+                encoding, original_offset
+            ))
+        };
+        let mut units = units.enumerate().peekable();
+        while let Some((index, unit)) = units.next() {
+            let original_offset = index * unit_size;
+            let char = if unit_size == 2 && (0xD800..0xDC00).contains(&unit) {
+                // high surrogate, which must be followed by a low surrogate
+                let low = units
+                    .next_if(|(_, low)| (0xDC00..0xE000).contains(low))
+                    .ok_or_else(|| invalid(original_offset))?
+                    .1;
+                char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
+            } else {
+                char::from_u32(unit)
+            };
+            let char = char.ok_or_else(|| invalid(original_offset))?;
+            offsets.push((text.len(), original_offset));
+            text.push(char);
+        }
+
+        Ok(Self {
+            text,
+            encoding,
+            offsets,
+            original_len: bytes.len(),
+        })
+    }
+
+    /// Maps a byte offset in the decoded text back to the original bytes.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        if self.offsets.is_empty() {
+            return offset;
+        }
+        match self
+            .offsets
+            .binary_search_by_key(&offset, |&(decoded, _)| decoded)
+        {
+            Ok(index) => self.offsets[index].1,
+            // past the end of the text: the end of the original bytes.
+            Err(index) if index == self.offsets.len() => self.original_len,
+            Err(index) => self.offsets[index].1,
+        }
+    }
+
+    /// Maps the offsets of a diagnostic back to the original bytes.
+    pub fn map_diagnostic(&self, diagnostic: &mut InvalidSyntaxDiagnostic) {
+        let span = diagnostic.span();
+        diagnostic.original_span = self.original_offset(span.start)..self.original_offset(span.end);
+    }
+
+    /// Maps the offsets of a syntax error back to the original bytes.
+    pub fn map_error(&self, error: FormatJsonError) -> FormatJsonError {
+        match error {
+            FormatJsonError::InvalidSyntax(mut diagnostic) => {
+                self.map_diagnostic(&mut diagnostic);
+                for other in &mut diagnostic.others {
+                    self.map_diagnostic(other);
+                }
+                FormatJsonError::InvalidSyntax(diagnostic)
+            }
+            error => error,
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Renders errors in machine-readable formats, for editors and CI tools.
+use std::str::FromStr;
+
+use miette::Severity;
+
+use crate::{
+    errors::{FormatJsonError, InvalidSyntaxDiagnostic},
+    format_json,
+    strings::quote,
+};
+
+/// The formats errors can be reported in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// Human readable output, rendered by [miette].
+    #[default]
+    Fancy,
+    /// A JSON array of diagnostics.
+    Json,
+    /// A [SARIF 2.1.0](https://sarifweb.azurewebsites.net/) log, for code
+    /// scanning dashboards.
+    Sarif,
+    /// GitHub Actions workflow commands, which show up as annotations.
+    Github,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "fancy" => Ok(ErrorFormat::Fancy),
+            "json" => Ok(ErrorFormat::Json),
+            "sarif" => Ok(ErrorFormat::Sarif),
+            "github" => Ok(ErrorFormat::Github),
+            _ => Err(format!(
+                "Invalid error format {:?}, expected fancy, json, sarif or github",
+                value
+            )),
+        }
+    }
+}
+
+/// The location of a diagnostic in a file. Lines and columns are one-based.
+struct Location {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    utf16_column: usize,
+    end_line: usize,
+    end_column: usize,
+    end_utf16_column: usize,
+}
+
+/// A diagnostic, flattened into the fields the machine-readable formats need.
+struct Record {
+    file: String,
+    severity: Severity,
+    code: String,
+    message: String,
+    help: Option<String>,
+    location: Option<Location>,
+}
+
+impl Record {
+    /// Flattens the error into records, one for each syntax error it holds.
+    fn from_error(filepath: &str, error: &FormatJsonError) -> Vec<Self> {
+        match error {
+            FormatJsonError::InvalidSyntax(diagnostic) => diagnostic
+                .errors()
+                .map(|diagnostic| Record::from_diagnostic(filepath, diagnostic))
+                .collect(),
+            _ => vec![Record {
+                file: filepath.to_string(),
+                severity: Severity::Error,
+                code: error.code().to_string(),
+                message: error.to_string(),
+                help: None,
+                location: None,
+            }],
+        }
+    }
+
+    fn from_diagnostic(filepath: &str, diagnostic: &InvalidSyntaxDiagnostic) -> Self {
+        let range = diagnostic.line_col_range();
+        let span = diagnostic.original_span();
+        Record {
+            file: filepath.to_string(),
+            severity: diagnostic.severity(),
+            code: diagnostic.code().to_string(),
+            message: diagnostic.message().to_string(),
+            help: Some(diagnostic.help().to_string()),
+            location: Some(Location {
+                start: span.start,
+                end: span.end,
+                line: range.start.line + 1,
+                column: range.start.column + 1,
+                utf16_column: range.start.utf16_column + 1,
+                end_line: range.end.line + 1,
+                end_column: range.end.column + 1,
+                end_utf16_column: range.end.utf16_column + 1,
+            }),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let mut fields = vec![
+            format!("\"file\": {}", quote(&self.file)),
+            format!(
+                "\"severity\": \"{}\"",
+                match self.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Advice => "advice",
+                }
+            ),
+            format!("\"code\": {}", quote(&self.code)),
+            format!("\"message\": {}", quote(&self.message)),
+        ];
+        if let Some(help) = &self.help {
+            fields.push(format!("\"help\": {}", quote(help)));
+        }
+        if let Some(location) = &self.location {
+            fields.push(format!(
+                "\"span\": {{\"start\": {}, \"end\": {}}}",
+                location.start, location.end
+            ));
+            fields.push(format!("\"line\": {}", location.line));
+            fields.push(format!("\"column\": {}", location.column));
+            fields.push(format!("\"end_line\": {}", location.end_line));
+            fields.push(format!("\"end_column\": {}", location.end_column));
+        }
+        format!("{{{}}}", fields.join(", "))
+    }
+
+    fn to_sarif(&self) -> String {
+        let mut location = format!("\"artifactLocation\": {{\"uri\": {}}}", quote(&self.file));
+        if let Some(loc) = &self.location {
+            // SARIF counts columns in UTF-16 code units by default.
+            location.push_str(&format!(
+                ", \"region\": {{\"startLine\": {}, \"startColumn\": {}, \"endLine\": {}, \
+                 \"endColumn\": {}, \"byteOffset\": {}, \"byteLength\": {}}}",
+                loc.line,
+                loc.utf16_column,
+                loc.end_line,
+                loc.end_utf16_column,
+                loc.start,
+                loc.end - loc.start,
+            ));
+        }
+        format!(
+            "{{\"ruleId\": {}, \"level\": \"{}\", \"message\": {{\"text\": {}}}, \
+             \"locations\": [{{\"physicalLocation\": {{{}}}}}]}}",
+            quote(&self.code),
+            match self.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Advice => "note",
+            },
+            quote(&self.message),
+            location
+        )
+    }
+
+    fn to_github(&self) -> String {
+        let mut properties = vec![format!("file={}", escape_property(&self.file))];
+        if let Some(location) = &self.location {
+            properties.push(format!("line={}", location.line));
+            properties.push(format!("col={}", location.column));
+            properties.push(format!("endLine={}", location.end_line));
+            properties.push(format!("endColumn={}", location.end_column));
+        }
+        properties.push(format!("title={}", escape_property(&self.code)));
+        let command = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Advice => "notice",
+        };
+        format!(
+            "::{} {}::{}\n",
+            command,
+            properties.join(","),
+            escape_data(&self.message)
+        )
+    }
+}
+
+/// Escapes the message of a GitHub workflow command.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property value of a GitHub workflow command.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+impl ErrorFormat {
+    /// Renders the errors raised while formatting the given files.
+    ///
+    /// Returns `None` for [ErrorFormat::Fancy], which is rendered by [miette].
+    pub fn render(&self, errors: &[(&str, &FormatJsonError)]) -> Option<String> {
+        self.render_records(
+            errors
+                .iter()
+                .flat_map(|(filepath, error)| Record::from_error(filepath, error)),
+        )
+    }
+
+    /// Renders diagnostics, such as warnings, raised while formatting the given files.
+    ///
+    /// Returns `None` for [ErrorFormat::Fancy], which is rendered by [miette].
+    pub fn render_diagnostics(
+        &self,
+        diagnostics: &[(&str, &InvalidSyntaxDiagnostic)],
+    ) -> Option<String> {
+        self.render_records(
+            diagnostics
+                .iter()
+                .map(|(filepath, diagnostic)| Record::from_diagnostic(filepath, diagnostic)),
+        )
+    }
+
+    fn render_records(&self, records: impl Iterator<Item = Record>) -> Option<String> {
+        let output = match self {
+            ErrorFormat::Fancy => return None,
+            ErrorFormat::Json => {
+                let records: Vec<_> = records.map(|record| record.to_json()).collect();
+                format!("[{}]", records.join(", "))
+            }
+            ErrorFormat::Sarif => {
+                let results: Vec<_> = records.map(|record| record.to_sarif()).collect();
+                format!(
+                    "{{\"version\": \"2.1.0\", \
+                     \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\", \
+                     \"runs\": [{{\"tool\": {{\"driver\": {{\"name\": \"formatjson\", \
+                     \"version\": {}, \"informationUri\": {}}}}}, \"results\": [{}]}}]}}",
+                    quote(env!("CARGO_PKG_VERSION")),
+                    quote(env!("CARGO_PKG_HOMEPAGE")),
+                    results.join(", ")
+                )
+            }
+            ErrorFormat::Github => return Some(records.map(|record| record.to_github()).collect()),
+        };
+        Some(format_json(&output).expect("Rendered diagnostics should be valid JSON"))
+    }
+}
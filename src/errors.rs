@@ -1,8 +1,10 @@
 //! Errors raised by the library.
-use std::io;
+use std::{io, ops::Range, sync::Arc};
 
 use thiserror::Error;
 
+use crate::line_index::{LineCol, LineIndex};
+
 /// Error class raised by the library.
 #[derive(Error, Debug)]
 pub enum FormatJsonError {
@@ -12,23 +14,224 @@ pub enum FormatJsonError {
     /// Other, unexpected i/o errors, such as `PermissionDenied`.
     #[error("{0}")]
     IOError(#[from] io::Error),
-    /// The JSON file has invalid syntax.
+    /// The JSON file is not valid UTF-8, UTF-16 or UTF-32.
+    #[error("Invalid encoding: {0}")]
+    InvalidEncoding(String),
+    /// The JSON file has invalid syntax, or exceeds one of the configured
+    /// [limits][crate::limits].
     #[error("{0}")]
     InvalidSyntax(#[from] InvalidSyntaxDiagnostic),
+    /// The JSON Schema can't be used, e.g. it has an invalid `$ref`.
+    #[error("Invalid schema: {0}")]
+    InvalidSchema(String),
     /// Unexpected error.
     #[error("{0}")]
     Unknown(String),
 }
 
+impl FormatJsonError {
+    /// Returns the stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            FormatJsonError::FileNotFound(_) => "formatjson::file_not_found",
+            FormatJsonError::IOError(_) => "formatjson::io_error",
+            FormatJsonError::InvalidEncoding(_) => "formatjson::invalid_encoding",
+            FormatJsonError::InvalidSyntax(diagnostic) => diagnostic.code(),
+            FormatJsonError::InvalidSchema(_) => "formatjson::invalid_schema",
+            FormatJsonError::Unknown(_) => "formatjson::unknown",
+        }
+    }
+}
+
+/// Stable codes identifying each kind of syntax error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Generic invalid syntax, for diagnostics created outside the library.
+    InvalidSyntax,
+    EmptyFile,
+    UnexpectedToken,
+    UnexpectedEof,
+    TrailingComma,
+    MissingComma,
+    MissingColon,
+    InvalidKey,
+    UnterminatedString,
+    ControlCharacterInString,
+    InvalidEscape,
+    InvalidNumber,
+    InvalidLiteral,
+    UnexpectedCharacter,
+    SingleQuotedString,
+    UnquotedKey,
+    PythonLiteral,
+    TrailingText,
+    NonAsciiWhitespace,
+    DuplicateKey,
+    LimitExceeded,
+    SchemaViolation,
+    NumberOutOfRange,
+    LoneSurrogate,
+    InexactNumber,
+    UnsafeInteger,
+    Noncharacter,
+    ScalarTopLevel,
+}
+
+impl ErrorCode {
+    /// Returns the code as a string, e.g. `formatjson::trailing_comma`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidSyntax => "formatjson::invalid_syntax",
+            ErrorCode::EmptyFile => "formatjson::empty_file",
+            ErrorCode::UnexpectedToken => "formatjson::unexpected_token",
+            ErrorCode::UnexpectedEof => "formatjson::unexpected_eof",
+            ErrorCode::TrailingComma => "formatjson::trailing_comma",
+            ErrorCode::MissingComma => "formatjson::missing_comma",
+            ErrorCode::MissingColon => "formatjson::missing_colon",
+            ErrorCode::InvalidKey => "formatjson::invalid_key",
+            ErrorCode::UnterminatedString => "formatjson::unterminated_string",
+            ErrorCode::ControlCharacterInString => "formatjson::control_character_in_string",
+            ErrorCode::InvalidEscape => "formatjson::invalid_escape",
+            ErrorCode::InvalidNumber => "formatjson::invalid_number",
+            ErrorCode::InvalidLiteral => "formatjson::invalid_literal",
+            ErrorCode::UnexpectedCharacter => "formatjson::unexpected_character",
+            ErrorCode::SingleQuotedString => "formatjson::single_quoted_string",
+            ErrorCode::UnquotedKey => "formatjson::unquoted_key",
+            ErrorCode::PythonLiteral => "formatjson::python_literal",
+            ErrorCode::TrailingText => "formatjson::trailing_text",
+            ErrorCode::NonAsciiWhitespace => "formatjson::non_ascii_whitespace",
+            ErrorCode::DuplicateKey => "formatjson::duplicate_key",
+            ErrorCode::LimitExceeded => "formatjson::limit_exceeded",
+            ErrorCode::SchemaViolation => "formatjson::schema_violation",
+            ErrorCode::NumberOutOfRange => "formatjson::number_out_of_range",
+            ErrorCode::LoneSurrogate => "formatjson::lone_surrogate",
+            ErrorCode::InexactNumber => "formatjson::inexact_number",
+            ErrorCode::UnsafeInteger => "formatjson::unsafe_integer",
+            ErrorCode::Noncharacter => "formatjson::noncharacter",
+            ErrorCode::ScalarTopLevel => "formatjson::scalar_top_level",
+        }
+    }
+
+    /// Returns a message suggesting how to fix the error.
+    pub fn help(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidSyntax => "Check the JSON syntax around this point",
+            ErrorCode::EmptyFile => "A JSON file must contain a value, such as {} or []",
+            ErrorCode::UnexpectedToken => {
+                "Expected a value, or a comma or closing bracket after a value"
+            }
+            ErrorCode::UnexpectedEof => "Close every open bracket and brace",
+            ErrorCode::TrailingComma => "JSON does not allow trailing commas, remove it",
+            ErrorCode::MissingComma => "Add a comma between the two values",
+            ErrorCode::MissingColon => "Add a colon between the key and its value",
+            ErrorCode::InvalidKey => "Object keys must be double quoted strings",
+            ErrorCode::UnterminatedString => "Add a closing double quote to the string",
+            ErrorCode::ControlCharacterInString => {
+                "Control characters must be escaped, e.g. write a newline as \\n"
+            }
+            ErrorCode::InvalidEscape => {
+                "The only escapes are \\\" \\\\ \\/ \\b \\f \\n \\r \\t, and \\u followed by 4 hex digits"
+            }
+            ErrorCode::InvalidNumber => {
+                "Numbers look like -12.5e3, without leading zeros, a leading + or a trailing dot"
+            }
+            ErrorCode::InvalidLiteral => "The only literals are true, false and null, in lowercase",
+            ErrorCode::UnexpectedCharacter => {
+                "Remove this character. Strings must use double quotes"
+            }
+            ErrorCode::SingleQuotedString => "JSON strings must use double quotes",
+            ErrorCode::UnquotedKey => "Object keys must be double quoted strings",
+            ErrorCode::PythonLiteral => "Use true, false and null instead of True, False and None",
+            ErrorCode::TrailingText => "Remove the text after the JSON value",
+            ErrorCode::NonAsciiWhitespace => {
+                "JSON only allows spaces, tabs and line breaks between values"
+            }
+            ErrorCode::DuplicateKey => {
+                "Remove or rename one of the members, most parsers keep only the last one"
+            }
+            ErrorCode::LimitExceeded => "Raise the limit if the file is trusted",
+            ErrorCode::SchemaViolation => "Change the value to match the JSON Schema",
+            ErrorCode::NumberOutOfRange => {
+                "Most parsers read numbers as doubles, which can't hold this one"
+            }
+            ErrorCode::LoneSurrogate => {
+                "Escape a surrogate together with its pair, e.g. \\ud83d\\ude00"
+            }
+            ErrorCode::InexactNumber => {
+                "Most parsers read numbers as doubles, write it with fewer digits or as a string"
+            }
+            ErrorCode::UnsafeInteger => {
+                "Most parsers read integers as doubles, write large integers as strings"
+            }
+            ErrorCode::Noncharacter => {
+                "Noncharacters are reserved for internal use, remove or replace it"
+            }
+            ErrorCode::ScalarTopLevel => "Wrap the value in an object or an array",
+        }
+    }
+}
+
 /// Creates a [miette::Diagnostic] pointing at an invalid JSON syntax.
-#[derive(Error, Debug, miette::Diagnostic)]
+#[derive(Error, Debug)]
 #[error("{error_message}")]
 pub struct InvalidSyntaxDiagnostic {
-    #[source_code]
-    src: miette::NamedSource<String>,
-    #[label("Error here")]
-    bad_bit: miette::SourceSpan,
+    /// The named source, shared by every diagnostic of the same file.
+    src: Arc<miette::NamedSource<Arc<str>>>,
+    pub(crate) bad_bit: miette::SourceSpan,
     error_message: String,
+    code: ErrorCode,
+    severity: miette::Severity,
+    /// Other places in the source related to the error, with their labels.
+    related: Vec<(miette::SourceSpan, String)>,
+    /// Range of the error in the original file, which differs from the
+    /// range in `src` when the file was not UTF-8 encoded.
+    pub(crate) original_span: Range<usize>,
+    /// Further errors found in the same source, after this one.
+    pub(crate) others: Vec<InvalidSyntaxDiagnostic>,
+}
+
+impl miette::Diagnostic for InvalidSyntaxDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.code.as_str()))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.code.help()))
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(self.severity)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&*self.src)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let label = match self.severity {
+            miette::Severity::Error => "Error here",
+            miette::Severity::Warning => "Warning here",
+            miette::Severity::Advice => "Note here",
+        };
+        let primary =
+            miette::LabeledSpan::new_primary_with_span(Some(label.to_string()), self.bad_bit);
+        let related = self
+            .related
+            .iter()
+            .map(|(span, label)| miette::LabeledSpan::new_with_span(Some(label.clone()), *span));
+        Some(Box::new(std::iter::once(primary).chain(related)))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn miette::Diagnostic> + 'a>> {
+        if self.others.is_empty() {
+            return None;
+        }
+        Some(Box::new(
+            self.others
+                .iter()
+                .map(|other| other as &dyn miette::Diagnostic),
+        ))
+    }
 }
 
 impl InvalidSyntaxDiagnostic {
@@ -37,11 +240,184 @@ impl InvalidSyntaxDiagnostic {
         src: &str,
         bad_bit: miette::SourceSpan,
         error_message: String,
+    ) -> Self {
+        Self::with_source(
+            Arc::new(miette::NamedSource::new(filepath, Arc::from(src))),
+            bad_bit,
+            error_message,
+        )
+    }
+
+    fn with_source(
+        src: Arc<miette::NamedSource<Arc<str>>>,
+        bad_bit: miette::SourceSpan,
+        error_message: String,
     ) -> Self {
         Self {
-            src: miette::NamedSource::new(filepath, src.to_string()),
+            src,
+            original_span: bad_bit.offset()..bad_bit.offset() + bad_bit.len(),
             bad_bit,
             error_message,
+            code: ErrorCode::InvalidSyntax,
+            severity: miette::Severity::Error,
+            related: vec![],
+            others: vec![],
+        }
+    }
+
+    /// Creates a diagnostic for another error in the same source, sharing it.
+    pub fn sibling(&self, bad_bit: miette::SourceSpan, error_message: String) -> Self {
+        Self::with_source(self.src.clone(), bad_bit, error_message)
+    }
+
+    /// Attaches further errors found in the same source.
+    pub fn with_others(mut self, others: Vec<InvalidSyntaxDiagnostic>) -> Self {
+        self.others = others;
+        self
+    }
+
+    /// Returns this error, followed by the further errors found in the source.
+    pub fn errors(&self) -> impl Iterator<Item = &InvalidSyntaxDiagnostic> {
+        std::iter::once(self).chain(&self.others)
+    }
+
+    /// Sets the code identifying the kind of error.
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Sets the severity, for diagnostics that are only warnings.
+    pub fn with_severity(mut self, severity: miette::Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Adds a label pointing at another place related to the error.
+    pub fn with_related(mut self, span: miette::SourceSpan, label: &str) -> Self {
+        self.related.push((span, label.to_string()));
+        self
+    }
+
+    /// Returns the byte offset of the error in the original file.
+    pub fn byte_offset(&self) -> usize {
+        self.original_span.start
+    }
+
+    /// Returns the byte range of the error in the original file.
+    pub fn original_span(&self) -> Range<usize> {
+        self.original_span.clone()
+    }
+
+    /// Returns the byte range of the error in the (UTF-8) source.
+    pub fn span(&self) -> Range<usize> {
+        self.bad_bit.offset()..self.bad_bit.offset() + self.bad_bit.len()
+    }
+
+    /// Returns the line and column where the error starts.
+    pub fn line_col(&self) -> LineCol {
+        LineIndex::new(self.src.inner()).line_col(self.bad_bit.offset())
+    }
+
+    /// Returns the lines and columns where the error starts and ends.
+    pub fn line_col_range(&self) -> Range<LineCol> {
+        LineIndex::new(self.src.inner()).range(self.span())
+    }
+
+    /// Returns the stable code identifying the kind of error.
+    pub fn code(&self) -> &'static str {
+        self.code.as_str()
+    }
+
+    /// Returns the kind of error.
+    pub fn error_code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// Returns a message suggesting how to fix the error.
+    pub fn help(&self) -> &'static str {
+        self.code.help()
+    }
+
+    /// Returns how severe the diagnostic is.
+    pub fn severity(&self) -> miette::Severity {
+        self.severity
+    }
+
+    /// Returns the other places in the source related to the error.
+    pub fn related(&self) -> impl Iterator<Item = (Range<usize>, &str)> {
+        self.related
+            .iter()
+            .map(|(span, label)| (span.offset()..span.offset() + span.len(), label.as_str()))
+    }
+
+    /// Returns the path of the file the error is in.
+    pub fn filepath(&self) -> &str {
+        self.src.name()
+    }
+
+    /// Returns the error message, without any location information.
+    pub fn message(&self) -> &str {
+        &self.error_message
+    }
+}
+
+/// A problem with a value of a parsed document, such as a duplicate key or a
+/// number a double can't hold, before it is reported as a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// Range of the value in the parsed source.
+    pub span: Range<usize>,
+    pub code: ErrorCode,
+    pub message: String,
+    /// Another range related to the issue, and its label.
+    pub related: Option<(Range<usize>, &'static str)>,
+}
+
+impl Issue {
+    pub fn new(span: Range<usize>, code: ErrorCode, message: String) -> Self {
+        Self {
+            span,
+            code,
+            message,
+            related: None,
+        }
+    }
+
+    /// Points at another range related to the issue, with a label.
+    pub fn with_related(mut self, span: Range<usize>, label: &'static str) -> Self {
+        self.related = Some((span, label));
+        self
+    }
+}
+
+/// Returns a diagnostic with the given severity for each issue.
+///
+/// `original_offset` maps offsets of the parsed source back to `source`, in
+/// case the parsed source was repaired first.
+pub fn diagnostics(
+    issues: &[Issue],
+    filepath: &str,
+    source: &str,
+    original_offset: impl Fn(usize) -> usize,
+    severity: miette::Severity,
+) -> Vec<InvalidSyntaxDiagnostic> {
+    let map = |span: &Range<usize>| original_offset(span.start)..original_offset(span.end);
+    let mut diagnostics: Vec<InvalidSyntaxDiagnostic> = vec![];
+    for issue in issues {
+        let span = map(&issue.span).into();
+        let message = issue.message.clone();
+        // all diagnostics share the source of the first one.
+        let mut diagnostic = match diagnostics.first() {
+            Some(first) => first.sibling(span, message),
+            None => InvalidSyntaxDiagnostic::new(filepath, source, span, message),
+        }
+        .with_code(issue.code)
+        .with_severity(severity);
+        if let Some((span, label)) = &issue.related {
+            diagnostic = diagnostic.with_related(map(span).into(), label);
         }
+        diagnostics.push(diagnostic);
     }
+    diagnostics
 }
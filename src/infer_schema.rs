@@ -0,0 +1,185 @@
+//! Infers a JSON Schema from sample documents.
+//!
+//! The inferred schema lists the types seen at every position, the keys
+//! present in every sample object as `required`, and an `enum` for strings
+//! and numbers that only take a few, repeated values. Array items are merged
+//! into a single schema, listing every type seen among them.
+use crate::{
+    duplicate_keys::DuplicateKeyPolicy,
+    format_json_with_options,
+    limits::Limits,
+    nodes::{JSONFile, JSONNode},
+    strings::quote,
+    FormatOptions,
+};
+
+/// Strings and numbers with more distinct values than this are not enums.
+const MAX_ENUM_VALUES: usize = 5;
+
+/// The draft of the inferred schemas.
+const SCHEMA_URI: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Everything seen at one position in the samples.
+#[derive(Default)]
+struct Shape {
+    objects: usize,
+    arrays: usize,
+    strings: usize,
+    integers: usize,
+    /// Numbers with a fraction or exponent, which are not integers.
+    numbers: usize,
+    booleans: usize,
+    nulls: usize,
+    /// Properties of the objects, in the order they were first seen, with the
+    /// number of objects they were seen in.
+    properties: Vec<(String, usize, Shape)>,
+    /// The merged shape of all array items.
+    items: Option<Box<Shape>>,
+    /// Distinct strings and numbers as JSON text, until there are too many.
+    values: Option<Vec<String>>,
+}
+
+impl Shape {
+    fn new() -> Self {
+        Self {
+            values: Some(vec![]),
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, node: &JSONNode) {
+        match node {
+            JSONNode::Object(object) => {
+                self.objects += 1;
+                for member in &object.members {
+                    let key = &member.key.value;
+                    let index = match self.properties.iter().position(|(k, _, _)| k == key) {
+                        Some(index) => index,
+                        None => {
+                            self.properties.push((key.clone(), 0, Shape::new()));
+                            self.properties.len() - 1
+                        }
+                    };
+                    let (_, count, shape) = &mut self.properties[index];
+                    *count += 1;
+                    shape.add(&member.value);
+                }
+            }
+            JSONNode::Array(array) => {
+                self.arrays += 1;
+                let items = self.items.get_or_insert_with(|| Box::new(Shape::new()));
+                for item in &array.items {
+                    items.add(item);
+                }
+            }
+            JSONNode::String(string) => {
+                self.strings += 1;
+                self.add_value(quote(&string.value));
+            }
+            JSONNode::Number(number) => {
+                if number.value.fract() == 0.0 && !number.raw.contains(['.', 'e', 'E']) {
+                    self.integers += 1;
+                } else {
+                    self.numbers += 1;
+                }
+                self.add_value(number.raw.clone());
+            }
+            JSONNode::Bool(_) => self.booleans += 1,
+            JSONNode::Null(_) => self.nulls += 1,
+        }
+    }
+
+    fn add_value(&mut self, value: String) {
+        let Some(values) = &mut self.values else {
+            return;
+        };
+        if !values.contains(&value) {
+            values.push(value);
+        }
+        if values.len() > MAX_ENUM_VALUES {
+            self.values = None;
+        }
+    }
+
+    /// Returns the schema as compact JSON text.
+    fn to_schema(&self, root: bool) -> String {
+        let mut fields = vec![];
+        if root {
+            fields.push(format!("\"$schema\": {}", quote(SCHEMA_URI)));
+        }
+
+        let types: Vec<_> = [
+            ("object", self.objects),
+            ("array", self.arrays),
+            ("string", self.strings),
+            ("integer", if self.numbers == 0 { self.integers } else { 0 }),
+            ("number", self.numbers),
+            ("boolean", self.booleans),
+            ("null", self.nulls),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(name, _)| quote(name))
+        .collect();
+        match &types[..] {
+            [] => {}
+            [name] => fields.push(format!("\"type\": {}", name)),
+            _ => fields.push(format!("\"type\": [{}]", types.join(", "))),
+        }
+
+        if let Some(values) = &self.values {
+            // only worth an enum if the values repeat.
+            let scalars = self.strings + self.integers + self.numbers;
+            let only_scalars = self.objects + self.arrays + self.booleans == 0;
+            if only_scalars && !values.is_empty() && scalars > values.len() {
+                let mut values = values.clone();
+                if self.nulls > 0 {
+                    values.push("null".to_string());
+                }
+                fields.push(format!("\"enum\": [{}]", values.join(", ")));
+            }
+        }
+
+        if self.objects > 0 {
+            let properties: Vec<_> = self
+                .properties
+                .iter()
+                .map(|(key, _, shape)| format!("{}: {}", quote(key), shape.to_schema(false)))
+                .collect();
+            fields.push(format!("\"properties\": {{{}}}", properties.join(", ")));
+            let required: Vec<_> = self
+                .properties
+                .iter()
+                .filter(|(_, count, _)| *count >= self.objects)
+                .map(|(key, _, _)| quote(key))
+                .collect();
+            if !required.is_empty() {
+                fields.push(format!("\"required\": [{}]", required.join(", ")));
+            }
+        }
+        if let Some(items) = &self.items {
+            fields.push(format!("\"items\": {}", items.to_schema(false)));
+        }
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+/// Infers a JSON Schema that all the documents match, formatted by
+/// [format_json_with_options].
+pub fn infer_schema(documents: &[JSONFile]) -> String {
+    let mut shape = Shape::new();
+    for document in documents {
+        shape.add(&document.value);
+    }
+    // the schema nests deeper than the documents, which were already checked.
+    let options = FormatOptions {
+        duplicate_keys: DuplicateKeyPolicy::Allow,
+        limits: Limits {
+            max_depth: usize::MAX,
+            ..Limits::default()
+        },
+        ..FormatOptions::default()
+    };
+    format_json_with_options(&shape.to_schema(true), &options)
+        .expect("Inferred schema should be valid JSON")
+}
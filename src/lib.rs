@@ -29,59 +29,436 @@
 //!   }
 //!   ```
 
-use std::{
-    fs,
-    io::{self, Write},
-};
+// the example matches the one in the README, which is a whole program.
+#![allow(clippy::needless_doctest_main)]
 
+use std::{fs, io};
+
+use duplicate_keys::DuplicateKeyPolicy;
+pub use edits::{format_edits, format_range, TextEdit};
+use encoding::{DecodedSource, Encoding, EncodingPolicy};
 pub use errors::FormatJsonError;
+use errors::Issue;
+use options::BomPolicy;
+pub use options::FormatOptions;
+use printer::Printer;
+use schema::{SchemaError, SchemaSource};
 use token_formatter::TokenFormatter;
 
+pub mod canonical;
+pub mod duplicate_keys;
+pub mod edits;
+pub mod encoding;
+pub mod error_format;
 pub mod errors;
+pub mod infer_schema;
+pub mod limits;
+pub mod line_index;
+pub mod lsp;
+pub mod nodes;
+pub mod numbers;
+pub mod options;
+pub mod parser;
+pub mod printer;
+pub mod profile;
+pub mod repair;
+pub mod schema;
+pub mod sort_keys;
+pub mod strings;
 pub mod token_formatter;
 pub mod tokenizer;
 pub mod validator;
 
+/// Formatted output, along with any warnings raised while formatting.
+#[derive(Debug)]
+pub struct Formatted<T = String> {
+    pub output: T,
+    pub warnings: Vec<errors::InvalidSyntaxDiagnostic>,
+}
+
 /// Reads, formats, and overwrites the given JSON file.
 ///
 /// Throws a [FormatJsonError] on invalid syntax, or failing to read/write the file.
 pub fn format_json_file(filepath: &str) -> Result<(), FormatJsonError> {
-    let source = fs::read_to_string(&filepath).map_err(|err| {
+    format_json_file_with_options(filepath, &FormatOptions::default())
+}
+
+/// Reads, formats, and overwrites the given JSON file, using the given options.
+///
+/// The file may be encoded in UTF-8, UTF-16 or UTF-32.
+///
+/// Throws a [FormatJsonError] on invalid syntax, or failing to read/write the file.
+pub fn format_json_file_with_options(
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<(), FormatJsonError> {
+    format_json_file_with_warnings(filepath, options)?;
+    Ok(())
+}
+
+/// Reads, formats, and overwrites the given JSON file, returning any warnings.
+///
+/// Throws a [FormatJsonError] on invalid syntax, or failing to read/write the file.
+pub fn format_json_file_with_warnings(
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<Vec<errors::InvalidSyntaxDiagnostic>, FormatJsonError> {
+    let bytes = read_file(filepath)?;
+    let formatted = format_bytes(&bytes, filepath, options)?;
+    fs::write(filepath, formatted.output)?;
+    Ok(formatted.warnings)
+}
+
+/// Returns a new, formatted JSON string.
+///
+/// Throws a [FormatJsonError] on invalid syntax.
+pub fn format_json(contents: &str) -> Result<String, FormatJsonError> {
+    format_json_with_options(contents, &FormatOptions::default())
+}
+
+/// Returns a new JSON string, formatted using the given options.
+///
+/// Throws a [FormatJsonError] on invalid syntax.
+pub fn format_json_with_options(
+    contents: &str,
+    options: &FormatOptions,
+) -> Result<String, FormatJsonError> {
+    Ok(format_json_with_warnings(contents, options)?.output)
+}
+
+/// Returns a new JSON string formatted using the given options, and any
+/// warnings raised while formatting it.
+///
+/// Throws a [FormatJsonError] on invalid syntax.
+pub fn format_json_with_warnings(
+    contents: &str,
+    options: &FormatOptions,
+) -> Result<Formatted, FormatJsonError> {
+    format_source(contents, "<string>", options)
+}
+
+/// Formats a JSON string like [format_json_with_warnings], as the contents of
+/// the file at `filepath`, without reading or writing the file.
+///
+/// The path picks the [key order rules][sort_keys::KeyOrderRule] that apply,
+/// and relative schema paths are resolved from it, as when formatting the
+/// file itself.
+///
+/// Throws a [FormatJsonError] on invalid syntax.
+pub fn format_json_as_file(
+    contents: &str,
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<Formatted, FormatJsonError> {
+    format_source(contents, filepath, options)
+}
+
+/// Repairs common mistakes in almost-JSON text, and formats the result.
+///
+/// Every repair made is returned as a warning. Check the [repair] module for
+/// the mistakes that can be repaired.
+///
+/// Throws a [FormatJsonError] on invalid syntax that can't be repaired.
+pub fn repair_json(contents: &str) -> Result<Formatted, FormatJsonError> {
+    let options = FormatOptions {
+        repair: true,
+        ..FormatOptions::default()
+    };
+    format_json_with_warnings(contents, &options)
+}
+
+/// Returns the canonical form of the JSON string, as defined by [RFC 8785].
+/// Check the [canonical] module for details.
+///
+/// Throws a [FormatJsonError] on invalid syntax, duplicate keys, or values
+/// that have no canonical form.
+///
+/// [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+pub fn canonicalize(contents: &str) -> Result<String, FormatJsonError> {
+    let options = FormatOptions {
+        canonical: true,
+        ..FormatOptions::default()
+    };
+    format_json_with_options(contents, &options)
+}
+
+/// Formats JSON bytes in any of the encodings allowed by the JSON spec.
+///
+/// The output is UTF-8, unless [FormatOptions::encoding] asks to preserve the
+/// original encoding.
+///
+/// Throws a [FormatJsonError] on invalid encoding or syntax.
+pub fn format_json_bytes(
+    contents: &[u8],
+    options: &FormatOptions,
+) -> Result<Vec<u8>, FormatJsonError> {
+    Ok(format_json_bytes_with_warnings(contents, options)?.output)
+}
+
+/// Formats JSON bytes like [format_json_bytes], also returning any warnings.
+///
+/// Throws a [FormatJsonError] on invalid encoding or syntax.
+pub fn format_json_bytes_with_warnings(
+    contents: &[u8],
+    options: &FormatOptions,
+) -> Result<Formatted<Vec<u8>>, FormatJsonError> {
+    format_bytes(contents, "<bytes>", options)
+}
+
+/// Formats JSON bytes like [format_json_bytes_with_warnings], as the contents
+/// of the file at `filepath`, which names the file in diagnostics. Check
+/// [format_json_as_file] for how else the path is used.
+///
+/// Throws a [FormatJsonError] on invalid encoding or syntax.
+pub fn format_json_bytes_as_file(
+    contents: &[u8],
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<Formatted<Vec<u8>>, FormatJsonError> {
+    format_bytes(contents, filepath, options)
+}
+
+/// Reads and parses the given JSON file into a [syntax tree][nodes].
+///
+/// The file may be encoded in UTF-8, UTF-16 or UTF-32.
+///
+/// Throws a [FormatJsonError] on invalid syntax, or failing to read the file.
+pub fn parse_json_file(
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<nodes::JSONFile, FormatJsonError> {
+    let bytes = read_file(filepath)?;
+    parse_bytes(&bytes, filepath, options)
+}
+
+/// Parses JSON bytes in any of the encodings allowed by the JSON spec into a
+/// [syntax tree][nodes].
+///
+/// Throws a [FormatJsonError] on invalid encoding or syntax.
+pub fn parse_json_bytes(
+    contents: &[u8],
+    options: &FormatOptions,
+) -> Result<nodes::JSONFile, FormatJsonError> {
+    parse_bytes(contents, "<bytes>", options)
+}
+
+/// Parses JSON bytes like [parse_json_bytes], as the contents of the file at
+/// `filepath`, which names the file in diagnostics.
+///
+/// Throws a [FormatJsonError] on invalid encoding or syntax.
+pub fn parse_json_bytes_as_file(
+    contents: &[u8],
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<nodes::JSONFile, FormatJsonError> {
+    parse_bytes(contents, filepath, options)
+}
+
+fn read_file(filepath: &str) -> Result<Vec<u8>, FormatJsonError> {
+    fs::read(filepath).map_err(|err| {
         if err.kind() == io::ErrorKind::NotFound {
             return FormatJsonError::FileNotFound(filepath.to_string());
         }
-        return FormatJsonError::Unknown(err.to_string());
-    })?;
+        FormatJsonError::Unknown(err.to_string())
+    })
+}
+
+fn parse_bytes(
+    contents: &[u8],
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<nodes::JSONFile, FormatJsonError> {
+    options.limits.check_size(contents.len(), filepath, "")?;
+    let source = DecodedSource::decode(contents)?;
+    parser::parse_with_limits(&source.text, filepath, &options.limits)
+        .map_err(|err| source.map_error(err))
+}
+
+fn format_bytes(
+    contents: &[u8],
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<Formatted<Vec<u8>>, FormatJsonError> {
+    options.limits.check_size(contents.len(), filepath, "")?;
+    let source = DecodedSource::decode(contents)?;
+    // readers need the byte order mark to tell UTF-16 and UTF-32 apart.
+    let keep_bom =
+        options.encoding == EncodingPolicy::Preserve && source.encoding != Encoding::Utf8;
+    let with_bom;
+    let options = match keep_bom {
+        true => {
+            with_bom = FormatOptions {
+                bom: BomPolicy::Preserve,
+                ..options.clone()
+            };
+            &with_bom
+        }
+        false => options,
+    };
+    let mut formatted =
+        format_source(&source.text, filepath, options).map_err(|err| source.map_error(err))?;
+    for warning in &mut formatted.warnings {
+        source.map_diagnostic(warning);
+    }
+    Ok(Formatted {
+        output: match options.encoding {
+            EncodingPolicy::Utf8 => formatted.output.into_bytes(),
+            EncodingPolicy::Preserve => source.encoding.encode(&formatted.output),
+        },
+        warnings: formatted.warnings,
+    })
+}
+
+/// Tokenizes, validates and formats the source, repairing it first if asked to.
+fn format_source(
+    original: &str,
+    filepath: &str,
+    options: &FormatOptions,
+) -> Result<Formatted, FormatJsonError> {
+    options
+        .limits
+        .check_size(original.len(), filepath, original)?;
+    let mut warnings = vec![];
+    let repaired = match options.repair {
+        true => Some(repair::repair(original, filepath)?),
+        false => None,
+    };
+    if let Some(repaired) = &repaired {
+        warnings.extend(repaired.diagnostics(filepath, original));
+    }
+    let source = repaired
+        .as_ref()
+        .map_or(original, |repaired| repaired.source.as_str());
 
-    let tokens = tokenizer::tokenize(&source, filepath.to_string())?;
+    let tokens = tokenizer::tokenize_with_limits(source, filepath.to_string(), &options.limits)?;
 
     // validate the tokens before formatting them.
-    if let Err(error) = validator::validate(&tokens) {
-        return Err(FormatJsonError::InvalidSyntax(
-            errors::InvalidSyntaxDiagnostic::new(
-                filepath,
-                &source,
-                error.byte_offset().into(),
-                error.to_string(),
-            ),
-        ));
+    let errors = validator::validate_with_limits(&tokens, options.max_errors, &options.limits);
+    if let Some(diagnostic) = validator::errors_to_diagnostic(&errors, filepath, source, &tokens) {
+        return Err(diagnostic.into());
     };
 
-    let formatter = TokenFormatter::new(tokens.into_iter());
-    let mut file = io::BufWriter::new(fs::File::create(filepath)?);
-    for formatted_token in formatter {
-        file.write(formatted_token.as_bytes())?;
+    // canonical JSON and I-JSON can't have duplicate keys, unless they are
+    // dropped.
+    let duplicate_policy = match options.duplicate_keys {
+        DuplicateKeyPolicy::Allow | DuplicateKeyPolicy::Warn
+            if options.canonical || options.profile.is_some() =>
+        {
+            DuplicateKeyPolicy::Error
+        }
+        policy => policy,
+    };
+    // point at the original source, even when it was repaired.
+    let original_offset = |offset| match &repaired {
+        Some(repaired) => repaired.original_offset(offset),
+        None => offset,
+    };
+    let report = |issues: &[Issue], severity| {
+        errors::diagnostics(issues, filepath, original, original_offset, severity)
+    };
+    // every error is reported at once, in one diagnostic.
+    let fail = |issues: &[Issue]| {
+        let mut diagnostics = report(issues, miette::Severity::Error).into_iter();
+        let first = diagnostics.next()?;
+        Some(first.with_others(diagnostics.collect()))
+    };
+
+    // reporting duplicates only needs the tokens, dropping them needs the tree.
+    let drops_duplicates = matches!(
+        duplicate_policy,
+        DuplicateKeyPolicy::KeepFirst | DuplicateKeyPolicy::KeepLast
+    );
+    let mut duplicates = vec![];
+    if !drops_duplicates && duplicate_policy != DuplicateKeyPolicy::Allow {
+        duplicates = duplicate_keys::check_tokens(&tokens);
     }
-    file.write(b"\n")?;
-    Ok(())
-}
 
-/// Returns a new, formatted JSON string.
-///
-/// Throws a [FormatJsonError] on invalid syntax.
-pub fn format_json(contents: &str) -> Result<String, FormatJsonError> {
-    let tokens = tokenizer::tokenize(contents, "<string>".to_string())?;
-    let mut formatted_string: String = TokenFormatter::new(tokens.into_iter()).collect();
-    formatted_string.push('\n');
-    return Ok(formatted_string);
+    let key_order = options.key_order.iter().find(|rule| rule.matches(filepath));
+    let needs_tree = drops_duplicates
+        || options.schema != SchemaSource::None
+        || options.sort_keys.is_some()
+        || key_order.is_some()
+        || options.canonical
+        || options.profile.is_some()
+        || !options.string_escapes.is_preserve()
+        || !options.numbers.is_preserve()
+        || options.uses_printer();
+    let mut tree = needs_tree.then(|| parser::from_tokens(&tokens));
+    let mut issues = vec![];
+    if let Some(tree) = &mut tree {
+        if drops_duplicates {
+            duplicates = duplicate_keys::check(tree, duplicate_policy);
+        }
+        if let Some(profile) = &options.profile {
+            issues = profile.check(tree);
+        }
+    }
+    let duplicates: Vec<_> = duplicates
+        .iter()
+        .map(|duplicate| duplicate.to_issue(duplicate_policy))
+        .collect();
+    match duplicate_policy {
+        // report duplicates along with the rest of the profile's violations.
+        DuplicateKeyPolicy::Error => {
+            issues.extend(duplicates);
+            issues.sort_by_key(|issue| issue.span.start);
+        }
+        _ => warnings.extend(report(&duplicates, miette::Severity::Warning)),
+    }
+    if let Some(error) = fail(&issues) {
+        return Err(error.into());
+    }
+
+    let mut formatted_string = options.bom.prefix(original).to_string();
+    match tree {
+        None => formatted_string.extend(TokenFormatter::with_options(
+            tokens.into_iter(),
+            original,
+            options,
+        )),
+        Some(mut tree) => {
+            if let Some(schema) = options.schema.load(&tree, filepath)? {
+                let errors = schema.validate(&tree)?;
+                let issues: Vec<_> = errors.iter().map(SchemaError::to_issue).collect();
+                if let Some(error) = fail(&issues) {
+                    return Err(error.into());
+                }
+            }
+
+            if options.canonical {
+                let output = canonical::to_canonical(&tree)
+                    .map_err(|issues| fail(&issues).expect("Errors should not be empty"))?;
+                return Ok(Formatted { output, warnings });
+            }
+
+            if let Some(sort_keys) = &options.sort_keys {
+                sort_keys.sort(&mut tree);
+            }
+            if let Some(key_order) = key_order {
+                key_order.apply(&mut tree);
+            }
+            if !options.string_escapes.is_preserve() {
+                options.string_escapes.apply(&mut tree);
+            }
+            if !options.numbers.is_preserve() {
+                let issues = options.numbers.apply(&mut tree);
+                warnings.extend(report(&issues, miette::Severity::Warning));
+            }
+
+            if options.uses_printer() {
+                formatted_string.push_str(&Printer::with_options(original, options).print(&tree));
+            } else {
+                formatted_string.extend(TokenFormatter::with_options(
+                    tree.tokens().into_iter(),
+                    original,
+                    options,
+                ));
+            }
+        }
+    }
+    formatted_string.push_str(options.line_ending.newline(original));
+    Ok(Formatted {
+        output: formatted_string,
+        warnings,
+    })
 }
@@ -0,0 +1,64 @@
+//! Limits that guard against hostile input, such as deeply nested arrays.
+use crate::errors::{ErrorCode, FormatJsonError, InvalidSyntaxDiagnostic};
+
+/// Limits enforced while reading a JSON file. Exceeding any of them is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum nesting depth of arrays and objects. Defaults to 512.
+    ///
+    /// The validator never recurses, but building the [syntax tree][crate::nodes]
+    /// does, so very high limits risk overflowing the stack.
+    pub max_depth: usize,
+    /// The maximum size of the file, in bytes. Unlimited by default.
+    pub max_size: usize,
+    /// The maximum length of a string, in bytes and excluding quotes.
+    /// Unlimited by default.
+    pub max_string_length: usize,
+    /// The maximum number of items in an array, or members in an object.
+    /// Unlimited by default.
+    pub max_members: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: 512,
+            max_size: usize::MAX,
+            max_string_length: usize::MAX,
+            max_members: usize::MAX,
+        }
+    }
+}
+
+impl Limits {
+    /// Throws a [FormatJsonError] if the file, `size` bytes long, is larger
+    /// than `max_size`.
+    ///
+    /// The error points at the text past the limit in `source`, which is empty
+    /// when the file isn't decoded yet.
+    pub fn check_size(
+        &self,
+        size: usize,
+        filepath: &str,
+        source: &str,
+    ) -> Result<(), FormatJsonError> {
+        if size > self.max_size {
+            let mut start = self.max_size.min(source.len());
+            while !source.is_char_boundary(start) {
+                start -= 1;
+            }
+            let message = format!(
+                "The file is {} bytes, more than the limit of {} bytes",
+                size, self.max_size
+            );
+            let diagnostic = InvalidSyntaxDiagnostic::new(
+                filepath,
+                source,
+                (start..source.len()).into(),
+                message,
+            );
+            return Err(diagnostic.with_code(ErrorCode::LimitExceeded).into());
+        }
+        Ok(())
+    }
+}
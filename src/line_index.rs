@@ -0,0 +1,94 @@
+//! Converts byte offsets in the source into line and column numbers.
+use std::{fmt::Display, ops::Range};
+
+/// A position in the source. All fields are zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: usize,
+    /// Column counted in characters (Unicode scalar values).
+    pub column: usize,
+    /// Column counted in UTF-16 code units, as used by most editors.
+    pub utf16_column: usize,
+}
+
+impl Display for LineCol {
+    /// Writes the position as one-based `line:column`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column + 1)
+    }
+}
+
+/// Stores the start of every line in the source, for fast lookups.
+#[derive(Debug)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Lines end with `\r\n`, `\r` or `\n`, like the tokenizer reads them.
+    pub fn new(source: &'a str) -> Self {
+        let bytes = source.as_bytes();
+        let line_ends = bytes.iter().enumerate().filter(|&(index, &byte)| {
+            byte == b'\n' || byte == b'\r' && bytes.get(index + 1) != Some(&b'\n')
+        });
+        let line_starts = std::iter::once(0)
+            .chain(line_ends.map(|(index, _)| index + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Returns the line and column of the given byte offset.
+    ///
+    /// Offsets past the end of the source are clamped to the end.
+    pub fn line_col(&self, byte_offset: usize) -> LineCol {
+        let mut byte_offset = byte_offset.min(self.source.len());
+        while !self.source.is_char_boundary(byte_offset) {
+            byte_offset -= 1;
+        }
+        let line = self
+            .line_starts
+            .partition_point(|&start| start <= byte_offset)
+            - 1;
+        let line_prefix = &self.source[self.line_starts[line]..byte_offset];
+        LineCol {
+            line,
+            column: line_prefix.chars().count(),
+            utf16_column: line_prefix.encode_utf16().count(),
+        }
+    }
+
+    /// Returns the byte offset of a position, with the column counted in
+    /// UTF-16 code units.
+    ///
+    /// Columns past the end of the line are clamped to before its line
+    /// ending, and lines past the end of the source to the end of the source.
+    pub fn offset(&self, line: usize, utf16_column: usize) -> usize {
+        let Some(&start) = self.line_starts.get(line) else {
+            return self.source.len();
+        };
+        let next = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.source.len(), |&next| next);
+        let text = &self.source[start..next];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let end = start + text.strip_suffix('\r').unwrap_or(text).len();
+        let mut units = 0;
+        for (index, char) in self.source[start..end].char_indices() {
+            if units >= utf16_column {
+                return start + index;
+            }
+            units += char.len_utf16();
+        }
+        end
+    }
+
+    /// Returns the start and end positions of the given byte range.
+    pub fn range(&self, range: Range<usize>) -> Range<LineCol> {
+        self.line_col(range.start)..self.line_col(range.end)
+    }
+}
@@ -0,0 +1,587 @@
+//! A [Language Server Protocol] server, so every editor can use the same
+//! formatter and validator for JSON files.
+//!
+//! The server talks over stdin and stdout. It formats documents and
+//! selections, publishes syntax errors and warnings as diagnostics, lists
+//! object keys as document symbols, folds arrays and objects, and shows the
+//! JSON pointer of the value under the cursor on hover. Documents are synced
+//! in full on every change, and positions are counted in UTF-16 code units.
+//!
+//! Messages are read with the crate's own [parser][crate::parser].
+//!
+//! [Language Server Protocol]: https://microsoft.github.io/language-server-protocol/
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Read, Write},
+    ops::Range,
+};
+
+use crate::{
+    edits::{format_edits, format_range, TextEdit},
+    errors::{FormatJsonError, InvalidSyntaxDiagnostic},
+    format_json_as_file,
+    line_index::{LineCol, LineIndex},
+    nodes::{JSONFile, JSONNode},
+    options::FormatOptions,
+    parser,
+    strings::quote,
+};
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// Serves the client until it sends `exit`, or closes the input.
+pub fn run(
+    mut input: impl BufRead,
+    mut output: impl Write,
+    options: &FormatOptions,
+) -> io::Result<()> {
+    let mut server = Server::new(options.clone());
+    while let Some(message) = read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the body of the next message, or `None` at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    // read what arrives rather than trusting the length up front.
+    let mut body = vec![];
+    input.take(length as u64).read_to_end(&mut body)?;
+    if body.len() < length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "The message is shorter than its Content-Length",
+        ));
+    }
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes a message body, with its header.
+pub fn write_message(output: &mut impl Write, message: &str) -> io::Result<()> {
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        message.len(),
+        message
+    )?;
+    output.flush()
+}
+
+/// The state of the server: the open documents, and the options used to
+/// format and validate them.
+pub struct Server {
+    options: FormatOptions,
+    /// Each open document, by URI.
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    /// Whether the client asked the server to exit.
+    pub exited: bool,
+}
+
+/// An open document.
+struct Document {
+    /// The path of the file, read from the URI, which picks the options that
+    /// depend on it such as key order rules.
+    path: String,
+    text: String,
+}
+
+impl Server {
+    pub fn new(options: FormatOptions) -> Self {
+        Self {
+            options,
+            documents: HashMap::new(),
+            shutdown: false,
+            exited: false,
+        }
+    }
+
+    /// Handles a message from the client, and returns the messages to send
+    /// back.
+    pub fn handle(&mut self, message: &str) -> Vec<String> {
+        let Ok(message) = parser::parse(message, "<message>") else {
+            return vec![error_response("null", PARSE_ERROR, "Invalid JSON message")];
+        };
+        // responses from the client have no method, and need no answer.
+        let Some(method) = get(&message.value, &["method"]).and_then(as_str) else {
+            return vec![];
+        };
+        let params = get(&message.value, &["params"]);
+        // the id is echoed back as written, whether it's a number, a string or null.
+        let id = match get(&message.value, &["id"]) {
+            Some(JSONNode::Number(number)) => number.raw.clone(),
+            Some(JSONNode::String(string)) => string.raw.clone(),
+            Some(JSONNode::Null(_)) => "null".to_string(),
+            Some(_) => return vec![error_response("null", INVALID_REQUEST, "Invalid id")],
+            None => return self.notify(method, params),
+        };
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Some("null".to_string())
+            }
+            _ if self.shutdown => {
+                return vec![error_response(
+                    &id,
+                    INVALID_REQUEST,
+                    "The server is shut down",
+                )];
+            }
+            "textDocument/formatting" => Some(self.formatting(params, false)),
+            "textDocument/rangeFormatting" => Some(self.formatting(params, true)),
+            "textDocument/documentSymbol" => Some(self.with_tree(params, document_symbols)),
+            "textDocument/foldingRange" => Some(self.with_tree(params, folding_ranges)),
+            "textDocument/hover" => Some(self.hover(params)),
+            _ => None,
+        };
+        match result {
+            Some(result) => vec![format!(
+                "{{\"jsonrpc\": \"2.0\", \"id\": {}, \"result\": {}}}",
+                id, result
+            )],
+            None => vec![error_response(
+                &id,
+                METHOD_NOT_FOUND,
+                &format!("Unknown method {}", method),
+            )],
+        }
+    }
+
+    /// Handles a notification, which gets no response.
+    fn notify(&mut self, method: &str, params: Option<&JSONNode>) -> Vec<String> {
+        let uri = params
+            .and_then(|params| get(params, &["textDocument", "uri"]))
+            .and_then(as_str)
+            .map(str::to_string);
+        let text = match method {
+            "textDocument/didOpen" => {
+                params.and_then(|params| get(params, &["textDocument", "text"]))
+            }
+            // the last change holds the whole text, as documents are synced in full.
+            "textDocument/didChange" => {
+                match params.and_then(|params| get(params, &["contentChanges"])) {
+                    Some(JSONNode::Array(changes)) => changes
+                        .items
+                        .last()
+                        .and_then(|change| get(change, &["text"])),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match (method, uri, text.and_then(as_str)) {
+            ("exit", _, _) => {
+                self.exited = true;
+                vec![]
+            }
+            ("textDocument/didClose", Some(uri), _) => {
+                self.documents.remove(&uri);
+                vec![publish_diagnostics(&uri, &[])]
+            }
+            (_, Some(uri), Some(text)) => {
+                let document = Document {
+                    path: uri_to_path(&uri),
+                    text: text.to_string(),
+                };
+                let diagnostics = self.diagnostics(&document);
+                self.documents.insert(uri.clone(), document);
+                vec![publish_diagnostics(&uri, &diagnostics)]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Returns the document the request is about.
+    fn document(&self, params: Option<&JSONNode>) -> Option<&Document> {
+        let uri = params
+            .and_then(|params| get(params, &["textDocument", "uri"]))
+            .and_then(as_str)?;
+        self.documents.get(uri)
+    }
+
+    /// Returns the edits formatting the document, or the requested range of
+    /// it. Documents with syntax errors aren't formatted.
+    fn formatting(&self, params: Option<&JSONNode>, in_range: bool) -> String {
+        let Some(Document { path, text }) = self.document(params) else {
+            return "null".to_string();
+        };
+        let index = LineIndex::new(text);
+        let edits = match in_range {
+            true => {
+                let range = params
+                    .and_then(|params| get(params, &["range"]))
+                    .and_then(|range| to_offsets(&index, range));
+                let Some(range) = range else {
+                    return "null".to_string();
+                };
+                format_range(text, range, &self.options)
+            }
+            false => format_edits(text, path, &self.options),
+        };
+        match edits {
+            Ok(edits) => {
+                let edits: Vec<_> = edits.iter().map(|edit| text_edit(&index, edit)).collect();
+                format!("[{}]", edits.join(", "))
+            }
+            Err(_) => "null".to_string(),
+        }
+    }
+
+    /// Parses the document, and builds the result from its tree. Documents
+    /// with syntax errors have no result.
+    fn with_tree(
+        &self,
+        params: Option<&JSONNode>,
+        build: fn(&str, &LineIndex, &JSONFile) -> String,
+    ) -> String {
+        let Some(Document { path, text }) = self.document(params) else {
+            return "null".to_string();
+        };
+        match parser::parse_with_limits(text, path, &self.options.limits) {
+            Ok(tree) => build(text, &LineIndex::new(text), &tree),
+            Err(_) => "null".to_string(),
+        }
+    }
+
+    /// Shows the JSON pointer of the value or key under the cursor.
+    fn hover(&self, params: Option<&JSONNode>) -> String {
+        let Some(Document { path, text }) = self.document(params) else {
+            return "null".to_string();
+        };
+        let Ok(tree) = parser::parse_with_limits(text, path, &self.options.limits) else {
+            return "null".to_string();
+        };
+        let index = LineIndex::new(text);
+        let position = params.and_then(|params| get(params, &["position"]));
+        let Some(offset) = position.and_then(|position| to_offset(&index, position)) else {
+            return "null".to_string();
+        };
+        let Some((pointer, span)) = pointer_at(&tree.value, offset, String::new()) else {
+            return "null".to_string();
+        };
+        let contents = match pointer.as_str() {
+            "" => "The root value".to_string(),
+            pointer => format!("`{}`", pointer),
+        };
+        format!(
+            "{{\"contents\": {{\"kind\": \"markdown\", \"value\": {}}}, \"range\": {}}}",
+            quote(&contents),
+            range(&index, span)
+        )
+    }
+
+    /// Returns the errors and warnings raised while formatting the document.
+    fn diagnostics(&self, document: &Document) -> Vec<String> {
+        let text = &document.text;
+        match format_json_as_file(text, &document.path, &self.options) {
+            Ok(formatted) => formatted.warnings.iter().map(diagnostic).collect(),
+            Err(FormatJsonError::InvalidSyntax(error)) => error.errors().map(diagnostic).collect(),
+            Err(error) => vec![format!(
+                "{{\"range\": {}, \"severity\": 1, \"code\": {}, \"source\": \"formatjson\", \
+                 \"message\": {}}}",
+                range(&LineIndex::new(text), 0..0),
+                quote(error.code()),
+                quote(&error.to_string())
+            )],
+        }
+    }
+}
+
+/// Returns the path of a `file://` URI, or the URI itself for other schemes,
+/// such as the `untitled:` documents that haven't been saved yet.
+pub fn uri_to_path(uri: &str) -> String {
+    let Some(path) = uri.strip_prefix("file://") else {
+        return uri.to_string();
+    };
+    // skip the host, which is usually empty.
+    let path = &path[path.find('/').unwrap_or(path.len())..];
+    let mut bytes = vec![];
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let path = String::from_utf8_lossy(&bytes).into_owned();
+    // Windows paths start with a drive letter, as in `/c:/Users`.
+    match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_string(),
+        _ => path,
+    }
+}
+
+fn capabilities() -> String {
+    format!(
+        "{{\"capabilities\": {{\"textDocumentSync\": 1, \"documentFormattingProvider\": true, \
+         \"documentRangeFormattingProvider\": true, \"documentSymbolProvider\": true, \
+         \"foldingRangeProvider\": true, \"hoverProvider\": true}}, \
+         \"serverInfo\": {{\"name\": \"formatjson\", \"version\": {}}}}}",
+        quote(env!("CARGO_PKG_VERSION"))
+    )
+}
+
+fn error_response(id: &str, code: i32, message: &str) -> String {
+    format!(
+        "{{\"jsonrpc\": \"2.0\", \"id\": {}, \"error\": {{\"code\": {}, \"message\": {}}}}}",
+        id,
+        code,
+        quote(message)
+    )
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: &[String]) -> String {
+    format!(
+        "{{\"jsonrpc\": \"2.0\", \"method\": \"textDocument/publishDiagnostics\", \
+         \"params\": {{\"uri\": {}, \"diagnostics\": [{}]}}}}",
+        quote(uri),
+        diagnostics.join(", ")
+    )
+}
+
+fn diagnostic(diagnostic: &InvalidSyntaxDiagnostic) -> String {
+    let severity = match diagnostic.severity() {
+        miette::Severity::Error => 1,
+        miette::Severity::Warning => 2,
+        miette::Severity::Advice => 3,
+    };
+    let line_cols = diagnostic.line_col_range();
+    format!(
+        "{{\"range\": {{\"start\": {}, \"end\": {}}}, \"severity\": {}, \"code\": {}, \
+         \"source\": \"formatjson\", \"message\": {}}}",
+        position(line_cols.start),
+        position(line_cols.end),
+        severity,
+        quote(diagnostic.code()),
+        quote(diagnostic.message())
+    )
+}
+
+fn text_edit(index: &LineIndex, edit: &TextEdit) -> String {
+    format!(
+        "{{\"range\": {}, \"newText\": {}}}",
+        range(index, edit.range.clone()),
+        quote(&edit.replacement)
+    )
+}
+
+/// Lists the members of every object as symbols, nested like the document.
+/// Arrays and objects in arrays are listed by their index.
+fn document_symbols(_text: &str, index: &LineIndex, tree: &JSONFile) -> String {
+    format!("[{}]", symbols(index, &tree.value).join(", "))
+}
+
+fn symbols(index: &LineIndex, node: &JSONNode) -> Vec<String> {
+    match node {
+        JSONNode::Object(object) => object
+            .members
+            .iter()
+            .map(|member| {
+                // editors reject empty names.
+                let name = match member.key.value.as_str() {
+                    "" => "\"\"",
+                    key => key,
+                };
+                let span = member.key.span.start..member.value.span().end;
+                symbol(index, name, span, member.key.span.clone(), &member.value)
+            })
+            .collect(),
+        JSONNode::Array(array) => array
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| matches!(item, JSONNode::Array(_) | JSONNode::Object(_)))
+            .map(|(position, item)| {
+                symbol(index, &position.to_string(), item.span(), item.span(), item)
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn symbol(
+    index: &LineIndex,
+    name: &str,
+    span: Range<usize>,
+    selection: Range<usize>,
+    value: &JSONNode,
+) -> String {
+    let kind = match value {
+        JSONNode::String(_) => 15,
+        JSONNode::Number(_) => 16,
+        JSONNode::Bool(_) => 17,
+        JSONNode::Array(_) => 18,
+        JSONNode::Object(_) => 19,
+        JSONNode::Null(_) => 21,
+    };
+    format!(
+        "{{\"name\": {}, \"kind\": {}, \"range\": {}, \"selectionRange\": {}, \"children\": [{}]}}",
+        quote(name),
+        kind,
+        range(index, span),
+        range(index, selection),
+        symbols(index, value).join(", ")
+    )
+}
+
+/// Folds every array and object that spans several lines, keeping the line
+/// of the closing bracket visible when it's on a line of its own.
+fn folding_ranges(text: &str, index: &LineIndex, tree: &JSONFile) -> String {
+    let mut ranges = vec![];
+    push_folding_ranges(text, index, &tree.value, &mut ranges);
+    format!("[{}]", ranges.join(", "))
+}
+
+fn push_folding_ranges(text: &str, index: &LineIndex, node: &JSONNode, ranges: &mut Vec<String>) {
+    let children: Vec<_> = match node {
+        JSONNode::Array(array) => array.items.iter().collect(),
+        JSONNode::Object(object) => object.members.iter().map(|member| &member.value).collect(),
+        _ => return,
+    };
+    let span = node.span();
+    let start_line = index.line_col(span.start).line;
+    let closing = index.line_col(span.end - 1);
+    let closing_starts_line = text[..span.end - 1]
+        .rsplit('\n')
+        .next()
+        .is_some_and(|line| line.trim().is_empty());
+    let end_line = match closing_starts_line {
+        true => closing.line.saturating_sub(1),
+        false => closing.line,
+    };
+    if end_line > start_line {
+        ranges.push(format!(
+            "{{\"startLine\": {}, \"endLine\": {}}}",
+            start_line, end_line
+        ));
+    }
+    for child in children {
+        push_folding_ranges(text, index, child, ranges);
+    }
+}
+
+/// Returns the JSON pointer of the innermost value or key at the offset, and
+/// its span.
+fn pointer_at(node: &JSONNode, offset: usize, pointer: String) -> Option<(String, Range<usize>)> {
+    let span = node.span();
+    if offset < span.start || offset > span.end {
+        return None;
+    }
+    let contains = |span: &Range<usize>| span.start <= offset && offset <= span.end;
+    match node {
+        JSONNode::Array(array) => {
+            for (position, item) in array.items.iter().enumerate() {
+                if contains(&item.span()) {
+                    return pointer_at(item, offset, format!("{}/{}", pointer, position));
+                }
+            }
+        }
+        JSONNode::Object(object) => {
+            for member in &object.members {
+                let key = member.key.value.replace('~', "~0").replace('/', "~1");
+                let member_pointer = format!("{}/{}", pointer, key);
+                if contains(&member.key.span) {
+                    return Some((member_pointer, member.key.span.clone()));
+                }
+                if contains(&member.value.span()) {
+                    return pointer_at(&member.value, offset, member_pointer);
+                }
+            }
+        }
+        _ => {}
+    }
+    Some((pointer, span))
+}
+
+/// Returns the node at the path of object keys.
+fn get<'a>(node: &'a JSONNode, path: &[&str]) -> Option<&'a JSONNode> {
+    path.iter().try_fold(node, |node, key| match node {
+        JSONNode::Object(object) => object.get(key),
+        _ => None,
+    })
+}
+
+fn as_str(node: &JSONNode) -> Option<&str> {
+    match node {
+        JSONNode::String(string) => Some(&string.value),
+        _ => None,
+    }
+}
+
+fn as_usize(node: &JSONNode) -> Option<usize> {
+    match node {
+        JSONNode::Number(number) => number.raw.parse().ok(),
+        _ => None,
+    }
+}
+
+fn position(position: LineCol) -> String {
+    format!(
+        "{{\"line\": {}, \"character\": {}}}",
+        position.line, position.utf16_column
+    )
+}
+
+fn range(index: &LineIndex, span: Range<usize>) -> String {
+    let line_cols = index.range(span);
+    format!(
+        "{{\"start\": {}, \"end\": {}}}",
+        position(line_cols.start),
+        position(line_cols.end)
+    )
+}
+
+/// Returns the byte offset of an LSP position.
+fn to_offset(index: &LineIndex, position: &JSONNode) -> Option<usize> {
+    let line = get(position, &["line"]).and_then(as_usize)?;
+    let character = get(position, &["character"]).and_then(as_usize)?;
+    Some(index.offset(line, character))
+}
+
+/// Returns the byte range of an LSP range.
+fn to_offsets(index: &LineIndex, range: &JSONNode) -> Option<Range<usize>> {
+    let start = to_offset(index, get(range, &["start"])?)?;
+    let end = to_offset(index, get(range, &["end"])?)?;
+    Some(start..end)
+}
@@ -1,44 +1,303 @@
-use std::{env, io::Read, process};
+use std::{
+    env,
+    io::{Read, Write},
+    process,
+};
 
+use formatjson::{
+    error_format::ErrorFormat,
+    errors::InvalidSyntaxDiagnostic,
+    profile::IJson,
+    sort_keys::{KeyOrderRule, SortKeys},
+    FormatOptions,
+};
 use miette::Result;
 
 const BOLD_RED: &str = "\x1b[1;31m";
 const BOLD_GREEN: &str = "\x1b[1;32m";
 const NORMAL: &str = "\x1b[m";
 
+/// The commands the binary can run.
+#[derive(PartialEq, Eq)]
+enum Command {
+    /// Validate and format a JSON file in-place, or stdin.
+    Format,
+    /// Print a JSON Schema inferred from the given files, or stdin.
+    InferSchema,
+    /// Run a Language Server Protocol server over stdin and stdout.
+    Lsp,
+}
+
+/// Command line arguments passed to the binary.
+struct Args {
+    command: Command,
+    filepaths: Vec<String>,
+    options: FormatOptions,
+    error_format: ErrorFormat,
+}
+
+/// Parses the arguments, which are an optional command, filepaths,
+/// `--option=value` flags, and switches such as `--fix`.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut command = Command::Format;
+    let mut filepaths = vec![];
+    let mut options = FormatOptions::default();
+    let mut error_format = ErrorFormat::default();
+    let mut sort_depths = None;
+    let mut require_container = false;
+
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            if arg == "infer-schema" && filepaths.is_empty() && command == Command::Format {
+                command = Command::InferSchema;
+            } else if arg == "lsp" && filepaths.is_empty() && command == Command::Format {
+                command = Command::Lsp;
+            } else {
+                filepaths.push(arg);
+            }
+            continue;
+        };
+        if flag == "fix" {
+            options.repair = true;
+            continue;
+        }
+        if flag == "canonical" {
+            options.canonical = true;
+            continue;
+        }
+        if flag == "require-container" {
+            require_container = true;
+            continue;
+        }
+        if flag == "tabular" {
+            options.tabular = true;
+            continue;
+        }
+        if flag == "unescape-slash" {
+            options.string_escapes.unescape_slash = true;
+            continue;
+        }
+        if flag == "lowercase-exponent" {
+            options.numbers.lowercase_exponent = true;
+            continue;
+        }
+        if flag == "strip-exponent-plus" {
+            options.numbers.strip_exponent_plus = true;
+            continue;
+        }
+        if flag == "strip-trailing-zeros" {
+            options.numbers.strip_trailing_zeros = true;
+            continue;
+        }
+        if flag == "number-warnings" {
+            options.numbers.warnings = true;
+            continue;
+        }
+        if flag == "fill-arrays" {
+            options.fill_arrays = true;
+            continue;
+        }
+        if flag == "matrix-rows" {
+            options.matrix_rows = true;
+            continue;
+        }
+        // Accept both `--flag=value` and `--flag value`
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Missing value for --{}", flag))?;
+                (flag.to_string(), value)
+            }
+        };
+        match name.as_str() {
+            "line-ending" => options.line_ending = value.parse()?,
+            "bom" => options.bom = value.parse()?,
+            "number-notation" => options.numbers.notation = value.parse()?,
+            "escapes" => options.string_escapes.mode = value.parse()?,
+            "escape-hex" => options.string_escapes.hex_case = value.parse()?,
+            "blank-lines" => options.blank_lines = value.parse()?,
+            "encoding" => options.encoding = value.parse()?,
+            "error-format" => error_format = value.parse()?,
+            "duplicate-keys" => options.duplicate_keys = value.parse()?,
+            "schema" => options.schema = value.parse()?,
+            "profile" => options.profile = Some(value.parse()?),
+            "sort-keys" => {
+                options
+                    .sort_keys
+                    .get_or_insert_with(SortKeys::default)
+                    .order = value.parse()?
+            }
+            "key-order" if value == "presets" => options.key_order.extend(KeyOrderRule::presets()),
+            "key-order" => options.key_order.push(value.parse()?),
+            "sort-depths" => {
+                let depths = value
+                    .split(',')
+                    .map(|depth| parse_number(&name, depth.trim()))
+                    .collect::<Result<_, _>>()?;
+                sort_depths = Some(depths);
+            }
+            "max-line-width" => options.max_line_width = Some(parse_number(&name, &value)?),
+            "align-values" => options.align_values = Some(parse_number(&name, &value)?),
+            "max-errors" => options.max_errors = parse_number(&name, &value)?,
+            "max-depth" => options.limits.max_depth = parse_number(&name, &value)?,
+            "max-size" => options.limits.max_size = parse_number(&name, &value)?,
+            "max-string-length" => options.limits.max_string_length = parse_number(&name, &value)?,
+            "max-members" => options.limits.max_members = parse_number(&name, &value)?,
+            _ => return Err(format!("Unknown option --{}", name)),
+        }
+    }
+    if let Some(depths) = sort_depths {
+        options
+            .sort_keys
+            .get_or_insert_with(SortKeys::default)
+            .depths = Some(depths);
+    }
+    if require_container {
+        options
+            .profile
+            .get_or_insert_with(IJson::default)
+            .require_container = true;
+    }
+    // only infer-schema reads several files.
+    match command {
+        Command::Format if filepaths.len() > 1 => {
+            return Err(format!(
+                "Expected at most one file to format, found {}",
+                filepaths.len()
+            ));
+        }
+        Command::Lsp if !filepaths.is_empty() => {
+            return Err("The lsp command reads stdin, and takes no files".to_string());
+        }
+        _ => {}
+    }
+    Ok(Args {
+        command,
+        filepaths,
+        options,
+        error_format,
+    })
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number {:?} for --{}", value, name))
+}
+
 /// Reads a filepath argument, and validates and formats the JSON file in-place.
 fn main() -> Result<()> {
     let mut args = env::args();
 
-    // ignore executable name itself, we care about first arg: filepath
+    // ignore executable name itself, we care about the rest of the args
     args.next();
+    let args = parse_args(args).unwrap_or_else(|message| {
+        eprintln!("{}Error:{} {}", BOLD_RED, NORMAL, message);
+        process::exit(2);
+    });
+
+    if args.command == Command::InferSchema {
+        return infer_schema(&args);
+    }
+    if args.command == Command::Lsp {
+        let stdin = std::io::stdin().lock();
+        if let Err(error) = formatjson::lsp::run(stdin, std::io::stdout().lock(), &args.options) {
+            eprintln!("{}Error:{} {}", BOLD_RED, NORMAL, error);
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
     // Expect filepath as an arg, and print success message on finish
-    if let Some(filepath) = args.next() {
-        if let Err(error) = formatjson::format_json_file(&filepath) {
-            handle_error(error)?;
+    if let Some(filepath) = args.filepaths.first() {
+        match formatjson::format_json_file_with_warnings(filepath, &args.options) {
+            Ok(warnings) => print_warnings(filepath, warnings, args.error_format, false),
+            Err(error) => handle_error(filepath, error, args.error_format)?,
         }
         eprintln!("{}Success:{} formatted {}", BOLD_GREEN, NORMAL, filepath);
     // Otherwise expect JSON provided through stdin, and print output on stdout
     } else {
-        if let Err(error) = format_stdin() {
-            handle_error(error)?;
+        match format_stdin(&args.options) {
+            // stdout holds the formatted JSON, so warnings go to stderr.
+            Ok(warnings) => print_warnings("<stdin>", warnings, args.error_format, true),
+            Err(error) => handle_error("<stdin>", error, args.error_format)?,
         }
     }
     Ok(())
 }
 
-fn format_stdin() -> Result<(), formatjson::FormatJsonError> {
-    let mut buffer = String::new();
-    std::io::stdin().lock().read_to_string(&mut buffer)?;
-    let formatted_json = formatjson::format_json(&buffer)?;
-    println!("{}", formatted_json);
+/// Prints a JSON Schema inferred from the files given as arguments, or stdin.
+fn infer_schema(args: &Args) -> Result<()> {
+    let mut documents = vec![];
+    if args.filepaths.is_empty() {
+        let document = read_stdin().and_then(|buffer| {
+            formatjson::parse_json_bytes_as_file(&buffer, "<stdin>", &args.options)
+        });
+        match document {
+            Ok(document) => documents.push(document),
+            Err(error) => handle_error("<stdin>", error, args.error_format)?,
+        }
+    }
+    for filepath in &args.filepaths {
+        match formatjson::parse_json_file(filepath, &args.options) {
+            Ok(document) => documents.push(document),
+            Err(error) => handle_error(filepath, error, args.error_format)?,
+        }
+    }
+    print!("{}", formatjson::infer_schema::infer_schema(&documents));
     Ok(())
 }
 
-fn handle_error(error: formatjson::FormatJsonError) -> Result<()> {
+fn format_stdin(
+    options: &FormatOptions,
+) -> Result<Vec<InvalidSyntaxDiagnostic>, formatjson::FormatJsonError> {
+    let buffer = read_stdin()?;
+    let formatted = formatjson::format_json_bytes_as_file(&buffer, "<stdin>", options)?;
+    std::io::stdout().write_all(&formatted.output)?;
+    Ok(formatted.warnings)
+}
+
+fn read_stdin() -> Result<Vec<u8>, formatjson::FormatJsonError> {
+    let mut buffer = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+fn print_warnings(
+    filepath: &str,
+    warnings: Vec<InvalidSyntaxDiagnostic>,
+    error_format: ErrorFormat,
+    to_stderr: bool,
+) {
+    if warnings.is_empty() {
+        return;
+    }
+    let diagnostics: Vec<_> = warnings.iter().map(|warning| (filepath, warning)).collect();
+    match error_format.render_diagnostics(&diagnostics) {
+        Some(output) if to_stderr => eprint!("{}", output),
+        Some(output) => print!("{}", output),
+        None => {
+            for warning in warnings {
+                eprintln!("{:?}", miette::Report::new(warning));
+            }
+        }
+    }
+}
+
+fn handle_error(
+    filepath: &str,
+    error: formatjson::FormatJsonError,
+    error_format: ErrorFormat,
+) -> Result<()> {
+    // Machine-readable errors go to stdout, for tools to consume.
+    if let Some(output) = error_format.render(&[(filepath, &error)]) {
+        print!("{}", output);
+        process::exit(1);
+    }
     if let formatjson::FormatJsonError::InvalidSyntax(err) = error {
-        return Err(err.into());
+        Err(err.into())
     } else {
         eprintln!("{}Error:{} {}", BOLD_RED, NORMAL, error);
         process::exit(1);
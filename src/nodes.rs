@@ -1,33 +1,55 @@
-// This file is not used yet. But by adding a parser module that implements a
-// function to turn the tokens into these structs, and another function to go
-// from the nodes to tokens, we can implement serialization and deserialization
-// of JSON files.
-// It's also possible to implement a Visitor API to write lints for JSON files.
+//! Syntax tree of a JSON file, built from its tokens by the [parser][crate::parser].
+//!
+//! Every node keeps its byte range in the source, and scalars keep their
+//! source text, so the tree can be turned back into the same tokens.
+use std::ops::Range;
+
+use crate::tokenizer::{Token, TokenType};
+
 pub struct JSONFile {
     pub value: JSONNode,
+    /// Offsets of the tokens that follow a blank line in the source, sorted.
+    pub blank_lines: Vec<usize>,
 }
 
 pub struct JSONBool {
     pub value: bool,
+    pub span: Range<usize>,
 }
 
-pub struct JSONNull {}
+pub struct JSONNull {
+    pub span: Range<usize>,
+}
 
 pub struct JSONNumber {
     pub value: f64,
+    /// The number as written in the source, e.g. `1.50`.
+    pub raw: String,
+    pub span: Range<usize>,
 }
 
 pub struct JSONString {
+    /// The unescaped value of the string.
     pub value: String,
+    /// The string literal as written in the source, including quotes.
+    pub raw: String,
+    pub span: Range<usize>,
 }
 
 pub struct JSONArray {
     pub items: Vec<JSONNode>,
+    pub span: Range<usize>,
+}
+
+/// A key and value pair in an object.
+pub struct JSONMember {
+    pub key: JSONString,
+    pub value: JSONNode,
 }
 
 pub struct JSONObject {
-    pub keys: Vec<JSONNode>,
-    pub values: Vec<JSONNode>,
+    pub members: Vec<JSONMember>,
+    pub span: Range<usize>,
 }
 
 pub enum JSONNode {
@@ -38,3 +60,147 @@ pub enum JSONNode {
     Array(JSONArray),
     Object(JSONObject),
 }
+
+impl JSONNode {
+    /// Returns the byte range of the node in the source.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            JSONNode::Bool(node) => node.span.clone(),
+            JSONNode::Null(node) => node.span.clone(),
+            JSONNode::Number(node) => node.span.clone(),
+            JSONNode::String(node) => node.span.clone(),
+            JSONNode::Array(node) => node.span.clone(),
+            JSONNode::Object(node) => node.span.clone(),
+        }
+    }
+
+    /// Returns the JSON type of the node, e.g. `"object"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JSONNode::Bool(_) => "boolean",
+            JSONNode::Null(_) => "null",
+            JSONNode::Number(_) => "number",
+            JSONNode::String(_) => "string",
+            JSONNode::Array(_) => "array",
+            JSONNode::Object(_) => "object",
+        }
+    }
+
+    /// Returns true if both nodes hold the same value, ignoring formatting
+    /// and the order of object members.
+    pub fn equals(&self, other: &JSONNode) -> bool {
+        match (self, other) {
+            (JSONNode::Bool(a), JSONNode::Bool(b)) => a.value == b.value,
+            (JSONNode::Null(_), JSONNode::Null(_)) => true,
+            (JSONNode::Number(a), JSONNode::Number(b)) => a.value == b.value,
+            (JSONNode::String(a), JSONNode::String(b)) => a.value == b.value,
+            (JSONNode::Array(a), JSONNode::Array(b)) => {
+                a.items.len() == b.items.len()
+                    && a.items.iter().zip(&b.items).all(|(a, b)| a.equals(b))
+            }
+            (JSONNode::Object(a), JSONNode::Object(b)) => {
+                a.members.len() == b.members.len()
+                    && a.members.iter().all(|member| {
+                        b.get(&member.key.value)
+                            .is_some_and(|value| value.equals(&member.value))
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the node as compact JSON text on a single line.
+    pub fn to_compact_string(&self) -> String {
+        let mut text = String::new();
+        for token in self.tokens() {
+            text.push_str(&token.to_string());
+            if let TokenType::Comma | TokenType::Colon = token.token_type {
+                text.push(' ');
+            }
+        }
+        text
+    }
+
+    /// Returns the tokens of the node, as they'd be tokenized from the source.
+    pub fn tokens(&self) -> Vec<Token<'_>> {
+        let mut tokens = vec![];
+        self.push_tokens(&mut tokens);
+        tokens
+    }
+
+    fn push_tokens<'a>(&'a self, tokens: &mut Vec<Token<'a>>) {
+        match self {
+            JSONNode::Bool(node) => {
+                tokens.push(Token::new(TokenType::Boolean(node.value), node.span.start))
+            }
+            JSONNode::Null(node) => tokens.push(Token::new(TokenType::Null, node.span.start)),
+            JSONNode::Number(node) => {
+                tokens.push(Token::new(TokenType::Number(&node.raw), node.span.start))
+            }
+            JSONNode::String(node) => tokens.push(node.token()),
+            JSONNode::Array(node) => {
+                tokens.push(Token::new(TokenType::LeftBracket, node.span.start));
+                for (index, item) in node.items.iter().enumerate() {
+                    if index > 0 {
+                        push_comma(tokens);
+                    }
+                    item.push_tokens(tokens);
+                }
+                tokens.push(Token::new(TokenType::RightBracket, node.span.end - 1));
+            }
+            JSONNode::Object(node) => {
+                tokens.push(Token::new(TokenType::LeftBrace, node.span.start));
+                for (index, member) in node.members.iter().enumerate() {
+                    if index > 0 {
+                        push_comma(tokens);
+                    }
+                    tokens.push(member.key.token());
+                    tokens.push(Token::new(TokenType::Colon, member.key.span.end));
+                    member.value.push_tokens(tokens);
+                }
+                tokens.push(Token::new(TokenType::RightBrace, node.span.end - 1));
+            }
+        }
+    }
+}
+
+impl JSONObject {
+    /// Returns the value of the last member with the given key.
+    pub fn get(&self, key: &str) -> Option<&JSONNode> {
+        self.members
+            .iter()
+            .rev()
+            .find(|member| member.key.value == key)
+            .map(|member| &member.value)
+    }
+}
+
+impl JSONString {
+    fn token(&self) -> Token<'_> {
+        Token::new(TokenType::String(&self.raw), self.span.start)
+    }
+}
+
+/// Adds a comma right after the last token.
+fn push_comma(tokens: &mut Vec<Token>) {
+    let offset = tokens.last().map_or(0, |token| token.end_offset);
+    tokens.push(Token::new(TokenType::Comma, offset));
+}
+
+impl JSONFile {
+    /// Returns the tokens of the whole file.
+    pub fn tokens(&self) -> Vec<Token<'_>> {
+        let mut tokens = self.value.tokens();
+        if !self.blank_lines.is_empty() {
+            for token in &mut tokens {
+                token.blank_line_before = self.has_blank_line_before(token.byte_offset);
+            }
+        }
+        tokens
+    }
+
+    /// Whether the source had a blank line before the token at the offset.
+    pub fn has_blank_line_before(&self, offset: usize) -> bool {
+        self.blank_lines.binary_search(&offset).is_ok()
+    }
+}
@@ -0,0 +1,304 @@
+//! Normalizes how numbers are written, and warns about numbers that most
+//! parsers can't read exactly.
+//!
+//! Numbers are rewritten as text, so normalizing never changes their value,
+//! even when it doesn't fit in a double.
+use std::str::FromStr;
+
+use crate::{
+    canonical::es6_number,
+    errors::{ErrorCode, Issue},
+    nodes::{JSONFile, JSONNode, JSONNumber},
+};
+
+/// The largest integer that doubles, and so most parsers, hold exactly.
+const MAX_SAFE_INTEGER: &str = "9007199254740991";
+
+/// Whether numbers are written with an exponent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberNotation {
+    /// Keep the notation of each number.
+    #[default]
+    Preserve,
+    /// Write numbers without an exponent, like `1500` and `0.0015`. Numbers
+    /// with more than 21 digits before the point, or more than 5 zeros after
+    /// it, keep an exponent.
+    Plain,
+    /// Write numbers with one digit before the point and an exponent, like
+    /// `1.5e3` and `1.5e-3`.
+    Scientific,
+}
+
+impl FromStr for NumberNotation {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "preserve" => Ok(NumberNotation::Preserve),
+            "plain" => Ok(NumberNotation::Plain),
+            "scientific" => Ok(NumberNotation::Scientific),
+            _ => Err(format!(
+                "Invalid number notation {:?}, expected preserve, plain or scientific",
+                value
+            )),
+        }
+    }
+}
+
+/// How numbers are written. By default they are kept as written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumberFormat {
+    /// Numbers in another notation are rewritten in full, as if every other
+    /// option was set.
+    pub notation: NumberNotation,
+    /// Write `1E5` as `1e5`.
+    pub lowercase_exponent: bool,
+    /// Write `1e+5` as `1e5`.
+    pub strip_exponent_plus: bool,
+    /// Write `1.50` as `1.5`, and `1.0` as `1`.
+    pub strip_trailing_zeros: bool,
+    /// Warn about numbers that doubles can't hold exactly, and integers
+    /// outside of the range I-JSON considers safe.
+    pub warnings: bool,
+}
+
+impl NumberFormat {
+    /// Whether every number is written as it is in the source.
+    pub fn is_preserve(&self) -> bool {
+        *self == NumberFormat::default()
+    }
+
+    /// Rewrites the numbers in the file, and returns warnings about them if
+    /// asked to.
+    pub fn apply(&self, file: &mut JSONFile) -> Vec<Issue> {
+        let mut warnings = vec![];
+        self.apply_node(&mut file.value, &mut warnings);
+        warnings
+    }
+
+    fn apply_node(&self, node: &mut JSONNode, warnings: &mut Vec<Issue>) {
+        match node {
+            JSONNode::Number(number) => {
+                if self.warnings {
+                    warnings.extend(check(number));
+                }
+                number.raw = self.rewrite(&number.raw);
+            }
+            JSONNode::Array(array) => {
+                for item in &mut array.items {
+                    self.apply_node(item, warnings);
+                }
+            }
+            JSONNode::Object(object) => {
+                for member in &mut object.members {
+                    self.apply_node(&mut member.value, warnings);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the number, rewritten using the options.
+    pub fn rewrite(&self, raw: &str) -> String {
+        let parts = Parts::parse(raw);
+        match (self.notation, Decimal::new(&parts)) {
+            (NumberNotation::Plain, Some(decimal)) => return decimal.to_plain(),
+            (NumberNotation::Scientific, Some(decimal)) => return decimal.to_scientific(),
+            _ => {}
+        }
+
+        let mut fraction = parts.fraction;
+        if self.strip_trailing_zeros {
+            fraction = fraction.trim_end_matches('0');
+        }
+        let mut rewritten = format!("{}{}", parts.sign, parts.integer);
+        if !fraction.is_empty() {
+            rewritten.push('.');
+            rewritten.push_str(fraction);
+        }
+        if let Some(marker) = parts.exponent_marker {
+            rewritten.push(match self.lowercase_exponent {
+                true => 'e',
+                false => marker,
+            });
+            if parts.exponent_sign != "+" || !self.strip_exponent_plus {
+                rewritten.push_str(parts.exponent_sign);
+            }
+            rewritten.push_str(parts.exponent);
+        }
+        rewritten
+    }
+}
+
+/// The pieces of a number as written, e.g. `-`, `1`, `50`, `E`, `+`, `3`.
+struct Parts<'a> {
+    sign: &'a str,
+    integer: &'a str,
+    fraction: &'a str,
+    exponent_marker: Option<char>,
+    exponent_sign: &'a str,
+    exponent: &'a str,
+}
+
+impl<'a> Parts<'a> {
+    /// Splits a number that passed validation.
+    fn parse(raw: &'a str) -> Self {
+        let (sign, rest) = match raw.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", raw),
+        };
+        let (mantissa, exponent_marker, exponent) = match rest.find(['e', 'E']) {
+            Some(index) => (
+                &rest[..index],
+                rest[index..].chars().next(),
+                &rest[index + 1..],
+            ),
+            None => (rest, None, ""),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let (exponent_sign, exponent) = match exponent.strip_prefix(['+', '-']) {
+            Some(digits) => (&exponent[..1], digits),
+            None => ("", exponent),
+        };
+        Self {
+            sign,
+            integer,
+            fraction,
+            exponent_marker,
+            exponent_sign,
+            exponent,
+        }
+    }
+}
+
+/// A number as `digits × 10^exponent`, where the digits have no leading or
+/// trailing zeros, and are empty for zero.
+#[derive(Debug, PartialEq, Eq)]
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Returns `None` if the exponent doesn't fit in an `i64`.
+    fn new(parts: &Parts) -> Option<Self> {
+        let digits = format!("{}{}", parts.integer, parts.fraction);
+        let mut exponent = match parts.exponent {
+            "" => 0,
+            exponent => exponent.parse::<i64>().ok()?,
+        };
+        if parts.exponent_sign == "-" {
+            exponent = -exponent;
+        }
+        exponent = exponent.checked_sub(parts.fraction.len() as i64)?;
+
+        let trimmed = digits.trim_end_matches('0');
+        exponent = exponent.checked_add((digits.len() - trimmed.len()) as i64)?;
+        let digits = trimmed.trim_start_matches('0').to_string();
+        if digits.is_empty() {
+            exponent = 0;
+        }
+        Some(Self {
+            negative: parts.sign == "-",
+            digits,
+            exponent,
+        })
+    }
+
+    /// Returns the exponent of the number in scientific notation.
+    fn scientific_exponent(&self) -> i64 {
+        self.digits.len() as i64 + self.exponent - 1
+    }
+
+    fn sign(&self) -> &'static str {
+        if self.negative {
+            "-"
+        } else {
+            ""
+        }
+    }
+
+    fn to_plain(&self) -> String {
+        if self.digits.is_empty() {
+            return format!("{}0", self.sign());
+        }
+        // the decimal point goes after `point` digits.
+        let point = self.digits.len() as i64 + self.exponent;
+        if !(-5..=21).contains(&point) {
+            return self.to_scientific();
+        }
+        let number = if self.exponent >= 0 {
+            format!("{}{}", self.digits, "0".repeat(self.exponent as usize))
+        } else if point > 0 {
+            let (integer, fraction) = self.digits.split_at(point as usize);
+            format!("{}.{}", integer, fraction)
+        } else {
+            format!("0.{}{}", "0".repeat(-point as usize), self.digits)
+        };
+        format!("{}{}", self.sign(), number)
+    }
+
+    fn to_scientific(&self) -> String {
+        if self.digits.is_empty() {
+            return format!("{}0", self.sign());
+        }
+        let (first, rest) = self.digits.split_at(1);
+        let fraction = match rest {
+            "" => String::new(),
+            rest => format!(".{}", rest),
+        };
+        match self.scientific_exponent() {
+            0 => format!("{}{}{}", self.sign(), first, fraction),
+            exponent => format!("{}{}{}e{}", self.sign(), first, fraction, exponent),
+        }
+    }
+}
+
+/// Returns a warning if most parsers can't read the number exactly.
+pub(crate) fn check(number: &JSONNumber) -> Option<Issue> {
+    let warning = |code, message| Some(Issue::new(number.span.clone(), code, message));
+    if !number.value.is_finite() {
+        return warning(
+            ErrorCode::NumberOutOfRange,
+            format!("Number {} is too large for a double", number.raw),
+        );
+    }
+
+    let decimal = Decimal::new(&Parts::parse(&number.raw));
+    if let Some(decimal) = &decimal {
+        let integer_digits = decimal.digits.len() as i64 + decimal.exponent;
+        let is_unsafe = integer_digits > MAX_SAFE_INTEGER.len() as i64
+            || integer_digits == MAX_SAFE_INTEGER.len() as i64
+                && decimal.to_plain().trim_start_matches('-') > MAX_SAFE_INTEGER;
+        if decimal.exponent >= 0 && is_unsafe {
+            return warning(
+                ErrorCode::UnsafeInteger,
+                format!(
+                    "Integer {} is outside of the safe range of ±{}",
+                    number.raw, MAX_SAFE_INTEGER
+                ),
+            );
+        }
+    }
+
+    // the shortest digits that read back as the same double.
+    let read = Decimal::new(&Parts::parse(&format!("{:e}", number.value)));
+    let is_exact = match (&decimal, &read) {
+        (Some(decimal), Some(read)) => {
+            decimal.digits == read.digits && decimal.exponent == read.exponent
+        }
+        _ => false,
+    };
+    if !is_exact {
+        return warning(
+            ErrorCode::InexactNumber,
+            format!(
+                "Number {} can't be held exactly by a double, and reads as {}",
+                number.raw,
+                es6_number(number.value)
+            ),
+        );
+    }
+    None
+}
@@ -0,0 +1,218 @@
+//! Options that control how JSON is formatted.
+use std::str::FromStr;
+
+use crate::{
+    duplicate_keys::DuplicateKeyPolicy,
+    encoding::EncodingPolicy,
+    limits::Limits,
+    numbers::NumberFormat,
+    profile::IJson,
+    schema::SchemaSource,
+    sort_keys::{KeyOrderRule, SortKeys},
+    strings::StringEscapes,
+    tokenizer::BOM,
+};
+
+/// The line ending written by the formatter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    /// Unix style `\n` line endings.
+    #[default]
+    Lf,
+    /// Windows style `\r\n` line endings.
+    Crlf,
+    /// Use whichever line ending the source file uses, defaulting to `\n`.
+    Preserve,
+}
+
+impl LineEnding {
+    /// Returns the newline string to write for the given source.
+    pub fn newline(&self, source: &str) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Preserve => LineEnding::detect(source).newline(source),
+        }
+    }
+
+    /// Detects the line ending used by the source, based on its first line.
+    pub fn detect(source: &str) -> LineEnding {
+        match source.find('\n') {
+            Some(index) if source[..index].ends_with('\r') => LineEnding::Crlf,
+            _ => LineEnding::Lf,
+        }
+    }
+}
+
+impl FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            "preserve" => Ok(LineEnding::Preserve),
+            _ => Err(format!(
+                "Invalid line ending {:?}, expected lf, crlf or preserve",
+                value
+            )),
+        }
+    }
+}
+
+/// What to do with a UTF-8 byte order mark at the start of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BomPolicy {
+    /// Drop the byte order mark from the output.
+    #[default]
+    Strip,
+    /// Keep the byte order mark, if the source had one.
+    Preserve,
+}
+
+impl BomPolicy {
+    /// Returns the byte order mark to write before the output, if any.
+    pub fn prefix(&self, source: &str) -> &'static str {
+        match self {
+            BomPolicy::Preserve if source.starts_with(BOM) => "\u{FEFF}",
+            _ => "",
+        }
+    }
+}
+
+impl FromStr for BomPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "strip" => Ok(BomPolicy::Strip),
+            "preserve" => Ok(BomPolicy::Preserve),
+            _ => Err(format!(
+                "Invalid BOM policy {:?}, expected strip or preserve",
+                value
+            )),
+        }
+    }
+}
+
+/// What to do with blank lines between array items and object members.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlankLines {
+    /// Keep a single blank line wherever the source had one or more.
+    #[default]
+    Preserve,
+    /// Drop every blank line.
+    Remove,
+}
+
+impl FromStr for BlankLines {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "preserve" => Ok(BlankLines::Preserve),
+            "remove" => Ok(BlankLines::Remove),
+            _ => Err(format!(
+                "Invalid blank lines policy {:?}, expected preserve or remove",
+                value
+            )),
+        }
+    }
+}
+
+/// Options passed to the formatter.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// The line ending to write. Defaults to `\n`.
+    pub line_ending: LineEnding,
+    /// Whether to keep a leading byte order mark. Defaults to stripping it.
+    pub bom: BomPolicy,
+    /// The encoding files are written back in. Defaults to UTF-8.
+    ///
+    /// UTF-16 and UTF-32 written back in their encoding always keep their
+    /// byte order mark, whatever [bom][Self::bom] says, as readers need it to
+    /// detect the encoding.
+    pub encoding: EncodingPolicy,
+    /// Whether to keep blank lines between array items and object members.
+    pub blank_lines: BlankLines,
+    /// The maximum number of syntax errors to report. Defaults to 20.
+    pub max_errors: usize,
+    /// Whether to repair common mistakes before formatting, as described in
+    /// the [repair][crate::repair] module.
+    pub repair: bool,
+    /// How to handle objects that define the same key more than once.
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Limits that guard against hostile input.
+    pub limits: Limits,
+    /// The JSON Schema to validate documents against.
+    pub schema: SchemaSource,
+    /// How to sort object keys, if at all.
+    pub sort_keys: Option<SortKeys>,
+    /// Rules that put some keys first in matching files. The first rule that
+    /// matches the file is used.
+    pub key_order: Vec<KeyOrderRule>,
+    /// Whether to write the [canonical][crate::canonical] form instead, with
+    /// no whitespace. Duplicate keys are errors, unless the policy drops them.
+    pub canonical: bool,
+    /// Keep arrays and objects on one line when they fit in this width, using
+    /// the [printer][crate::printer]. By default they are always expanded.
+    pub max_line_width: Option<usize>,
+    /// Write arrays of objects that have the same keys as tables, with one
+    /// object per line and their members aligned in columns.
+    pub tabular: bool,
+    /// Line up the values of expanded objects by padding after the colons,
+    /// adding at most this many spaces.
+    pub align_values: Option<usize>,
+    /// Pack arrays of strings, numbers, booleans and nulls with as many items
+    /// per line as fit, rather than one item per line.
+    pub fill_arrays: bool,
+    /// Write arrays of arrays of numbers, like matrices, with one row per
+    /// line.
+    pub matrix_rows: bool,
+    /// How to write escapes in strings. Defaults to keeping them as written.
+    pub string_escapes: StringEscapes,
+    /// How to write numbers. Defaults to keeping them as written.
+    pub numbers: NumberFormat,
+    /// Check that documents conform to [I-JSON][crate::profile], and fail
+    /// with every value that doesn't.
+    pub profile: Option<IJson>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            line_ending: LineEnding::default(),
+            bom: BomPolicy::default(),
+            encoding: EncodingPolicy::default(),
+            blank_lines: BlankLines::default(),
+            max_errors: 20,
+            repair: false,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            limits: Limits::default(),
+            schema: SchemaSource::default(),
+            sort_keys: None,
+            key_order: vec![],
+            canonical: false,
+            max_line_width: None,
+            tabular: false,
+            align_values: None,
+            fill_arrays: false,
+            matrix_rows: false,
+            string_escapes: StringEscapes::default(),
+            numbers: NumberFormat::default(),
+            profile: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Whether the layout needs the [printer][crate::printer], rather than
+    /// the [TokenFormatter][crate::token_formatter::TokenFormatter].
+    pub(crate) fn uses_printer(&self) -> bool {
+        self.max_line_width.is_some()
+            || self.tabular
+            || self.align_values.is_some()
+            || self.fill_arrays
+            || self.matrix_rows
+    }
+}
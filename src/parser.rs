@@ -0,0 +1,125 @@
+//! Builds the syntax tree in [nodes][crate::nodes] from the tokens.
+use std::{iter::Peekable, slice::Iter};
+
+use crate::{
+    errors::FormatJsonError,
+    limits::Limits,
+    nodes::{
+        JSONArray, JSONBool, JSONFile, JSONMember, JSONNode, JSONNull, JSONNumber, JSONObject,
+        JSONString,
+    },
+    options::FormatOptions,
+    strings,
+    tokenizer::{self, Token, TokenType},
+    validator,
+};
+
+/// Tokenizes, validates and parses the source.
+///
+/// Throws a [FormatJsonError] on invalid syntax.
+pub fn parse(source: &str, filepath: &str) -> Result<JSONFile, FormatJsonError> {
+    parse_with_limits(source, filepath, &Limits::default())
+}
+
+/// Tokenizes, validates and parses the source, enforcing the limits.
+///
+/// Throws a [FormatJsonError] on invalid syntax, or if a limit is exceeded.
+pub fn parse_with_limits(
+    source: &str,
+    filepath: &str,
+    limits: &Limits,
+) -> Result<JSONFile, FormatJsonError> {
+    limits.check_size(source.len(), filepath, source)?;
+    let tokens = tokenizer::tokenize_with_limits(source, filepath.to_string(), limits)?;
+    let errors =
+        validator::validate_with_limits(&tokens, FormatOptions::default().max_errors, limits);
+    if let Some(diagnostic) = validator::errors_to_diagnostic(&errors, filepath, source, &tokens) {
+        return Err(diagnostic.into());
+    }
+    Ok(from_tokens(&tokens))
+}
+
+/// Builds the tree from tokens that passed [validator::validate_all].
+///
+/// Panics if the tokens are not valid JSON.
+pub fn from_tokens(tokens: &[Token]) -> JSONFile {
+    let blank_lines = tokens
+        .iter()
+        .filter(|token| token.blank_line_before)
+        .map(|token| token.byte_offset)
+        .collect();
+    let mut tokens = tokens.iter().peekable();
+    JSONFile {
+        value: parse_value(&mut tokens),
+        blank_lines,
+    }
+}
+
+fn parse_value(tokens: &mut Peekable<Iter<Token>>) -> JSONNode {
+    let token = tokens.next().expect("Tokens should be validated");
+    match token.token_type {
+        TokenType::String(_) => JSONNode::String(parse_string(token)),
+        TokenType::Number(raw) => JSONNode::Number(JSONNumber {
+            value: raw.parse().expect("Numbers should be validated"),
+            raw: raw.to_string(),
+            span: token.span(),
+        }),
+        TokenType::Boolean(value) => JSONNode::Bool(JSONBool {
+            value,
+            span: token.span(),
+        }),
+        TokenType::Null => JSONNode::Null(JSONNull { span: token.span() }),
+        TokenType::LeftBracket => {
+            let mut items = vec![];
+            let end = loop {
+                let next = tokens.peek().expect("Tokens should be validated");
+                match next.token_type {
+                    TokenType::RightBracket => break next.end_offset,
+                    TokenType::Comma => {
+                        tokens.next();
+                    }
+                    _ => items.push(parse_value(tokens)),
+                }
+            };
+            tokens.next(); // the closing bracket
+            JSONNode::Array(JSONArray {
+                items,
+                span: token.byte_offset..end,
+            })
+        }
+        TokenType::LeftBrace => {
+            let mut members = vec![];
+            let end = loop {
+                let next = tokens.next().expect("Tokens should be validated");
+                match next.token_type {
+                    TokenType::RightBrace => break next.end_offset,
+                    TokenType::Comma => continue,
+                    _ => {
+                        let key = parse_string(next);
+                        tokens.next(); // the colon
+                        members.push(JSONMember {
+                            key,
+                            value: parse_value(tokens),
+                        });
+                    }
+                }
+            };
+            JSONNode::Object(JSONObject {
+                members,
+                span: token.byte_offset..end,
+            })
+        }
+        _ => panic!("Tokens should be validated, found {}", token),
+    }
+}
+
+fn parse_string(token: &Token) -> JSONString {
+    let TokenType::String(raw) = token.token_type else {
+        panic!("Expected a string, found {}", token);
+    };
+    JSONString {
+        value: strings::unquote(raw),
+        raw: raw.to_string(),
+        span: token.span(),
+    }
+}
@@ -0,0 +1,374 @@
+//! Contains the printer, which lays out a syntax tree as a whole.
+//!
+//! Unlike [TokenFormatter][crate::token_formatter::TokenFormatter], which
+//! sees one token at a time and breaks after every bracket and comma, the
+//! printer can look at whole arrays and objects. It keeps them on one line
+//! when they fit in [FormatOptions::max_line_width], writes arrays of
+//! similar objects as tables when [FormatOptions::tabular] is set, lines up
+//! the values of objects when [FormatOptions::align_values] is set, and packs
+//! arrays of numbers when [FormatOptions::fill_arrays] or
+//! [FormatOptions::matrix_rows] are set.
+use crate::{
+    nodes::{JSONArray, JSONFile, JSONNode, JSONObject},
+    options::{BlankLines, FormatOptions},
+    token_formatter::INDENT,
+};
+
+/// The line width arrays are filled to, when there is no maximum width.
+pub const DEFAULT_FILL_WIDTH: usize = 80;
+
+/// Prints a syntax tree, choosing a layout for each array and object.
+pub struct Printer {
+    max_line_width: Option<usize>,
+    tabular: bool,
+    align_values: Option<usize>,
+    fill_arrays: bool,
+    matrix_rows: bool,
+    preserve_blank_lines: bool,
+    /// Offsets of the tokens that follow a blank line in the source.
+    blank_lines: Vec<usize>,
+    newline: &'static str,
+    output: String,
+    /// Characters written since the last newline.
+    column: usize,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Printer {
+    /// Creates a printer that expands every array and object, like
+    /// [TokenFormatter][crate::token_formatter::TokenFormatter].
+    pub fn new() -> Self {
+        Self {
+            max_line_width: None,
+            tabular: false,
+            align_values: None,
+            fill_arrays: false,
+            matrix_rows: false,
+            preserve_blank_lines: false,
+            blank_lines: vec![],
+            newline: "\n",
+            output: String::new(),
+            column: 0,
+        }
+    }
+
+    /// Creates a printer for a tree of the given source, using the options.
+    pub fn with_options(source: &str, options: &FormatOptions) -> Self {
+        Self {
+            max_line_width: options.max_line_width,
+            tabular: options.tabular,
+            align_values: options.align_values,
+            fill_arrays: options.fill_arrays,
+            matrix_rows: options.matrix_rows,
+            preserve_blank_lines: options.blank_lines == BlankLines::Preserve,
+            newline: options.line_ending.newline(source),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the formatted file, without a trailing newline.
+    pub fn print(mut self, file: &JSONFile) -> String {
+        if self.preserve_blank_lines {
+            self.blank_lines = file.blank_lines.clone();
+        }
+        self.write_node(&file.value, 0, 0);
+        self.output
+    }
+
+    /// Writes a node at the current position. `suffix` is the width of the
+    /// text that must follow it on the same line, such as a comma.
+    fn write_node(&mut self, node: &JSONNode, indent_level: usize, suffix: usize) {
+        match node {
+            JSONNode::Array(array) if !array.items.is_empty() => {
+                if self.tabular && self.write_table(array, indent_level) {
+                    return;
+                }
+                if self.fits(node, suffix) {
+                    return self.write_flat(node);
+                }
+                if self.matrix_rows && is_matrix(array) {
+                    return self.write_rows(array, indent_level);
+                }
+                if self.fill_arrays && array.items.iter().all(is_scalar) {
+                    return self.write_filled(array, indent_level);
+                }
+                self.write("[");
+                for (index, item) in array.items.iter().enumerate() {
+                    self.write_item_newline(index, item.span().start, indent_level + 1);
+                    let is_last = index + 1 == array.items.len();
+                    self.write_node(item, indent_level + 1, usize::from(!is_last));
+                    if !is_last {
+                        self.write(",");
+                    }
+                }
+                self.write_newline(indent_level);
+                self.write("]");
+            }
+            JSONNode::Object(object) if !object.members.is_empty() => {
+                if self.fits(node, suffix) {
+                    return self.write_flat(node);
+                }
+                let align_width = self.align_width(object);
+                self.write("{");
+                for (index, member) in object.members.iter().enumerate() {
+                    self.write_item_newline(index, member.key.span.start, indent_level + 1);
+                    self.write(&member.key.raw);
+                    self.write(":");
+                    let key_width = member.key.raw.chars().count();
+                    let padding = align_width.saturating_sub(key_width);
+                    self.write(&" ".repeat(padding + 1));
+                    let is_last = index + 1 == object.members.len();
+                    self.write_node(&member.value, indent_level + 1, usize::from(!is_last));
+                    if !is_last {
+                        self.write(",");
+                    }
+                }
+                self.write_newline(indent_level);
+                self.write("}");
+            }
+            _ => self.write_flat(node),
+        }
+    }
+
+    /// Whether the node fits on the rest of the line, followed by `suffix`.
+    /// Containers with blank lines between their items never do, so that the
+    /// blank lines are kept.
+    fn fits(&self, node: &JSONNode, suffix: usize) -> bool {
+        let mut item_offsets: Box<dyn Iterator<Item = usize>> = match node {
+            JSONNode::Array(array) => Box::new(array.items.iter().map(|item| item.span().start)),
+            JSONNode::Object(object) => {
+                Box::new(object.members.iter().map(|member| member.key.span.start))
+            }
+            _ => Box::new(std::iter::empty()),
+        };
+        // a blank line before the first item isn't kept.
+        item_offsets.next();
+        if item_offsets.any(|offset| self.blank_lines.binary_search(&offset).is_ok()) {
+            return false;
+        }
+        self.max_line_width.is_some_and(|max_line_width| {
+            let available = max_line_width.saturating_sub(self.column + suffix);
+            flat_width(node, available).is_some()
+        })
+    }
+
+    /// Returns the key width to pad keys to, so that the values of the object
+    /// line up. Keys are padded by at most [FormatOptions::align_values], so
+    /// keys longer than that are left out, rather than pushing every value
+    /// to the right.
+    fn align_width(&self, object: &JSONObject) -> usize {
+        let Some(max_padding) = self.align_values else {
+            return 0;
+        };
+        let key_widths = object
+            .members
+            .iter()
+            .map(|member| member.key.raw.chars().count());
+        let shortest = key_widths.clone().min().unwrap_or_default();
+        key_widths
+            .filter(|&width| width <= shortest + max_padding)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Writes an array of objects with the same keys as a table, with one
+    /// object per line and their members aligned in columns. Returns false,
+    /// writing nothing, if the array isn't one or the rows are too wide.
+    fn write_table(&mut self, array: &JSONArray, indent_level: usize) -> bool {
+        let Some(rows) = table_rows(array) else {
+            return false;
+        };
+        let cells: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| {
+                row.members
+                    .iter()
+                    .map(|member| {
+                        format!("{}: {}", member.key.raw, member.value.to_compact_string())
+                    })
+                    .collect()
+            })
+            .collect();
+        let column_widths: Vec<usize> = (0..cells[0].len())
+            .map(|column| {
+                cells
+                    .iter()
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        // braces, a comma and space between cells, and a comma after the row.
+        let row_width = 3 + column_widths.iter().sum::<usize>() + 2 * (column_widths.len() - 1);
+        let indent_width = INDENT.len() * (indent_level + 1);
+        if self
+            .max_line_width
+            .is_some_and(|max_line_width| indent_width + row_width > max_line_width)
+        {
+            return false;
+        }
+
+        self.write("[");
+        for (index, (row, object)) in cells.iter().zip(&rows).enumerate() {
+            self.write_item_newline(index, object.span.start, indent_level + 1);
+            self.write("{");
+            for (column, cell) in row.iter().enumerate() {
+                self.write(cell);
+                if column + 1 < row.len() {
+                    let padding = column_widths[column] - cell.chars().count() + 1;
+                    self.write(",");
+                    self.write(&" ".repeat(padding));
+                }
+            }
+            self.write("}");
+            if index + 1 < cells.len() {
+                self.write(",");
+            }
+        }
+        self.write_newline(indent_level);
+        self.write("]");
+        true
+    }
+
+    /// Writes an array of numeric arrays with one row per line, however wide.
+    fn write_rows(&mut self, array: &JSONArray, indent_level: usize) {
+        self.write("[");
+        for (index, row) in array.items.iter().enumerate() {
+            self.write_item_newline(index, row.span().start, indent_level + 1);
+            self.write_flat(row);
+            if index + 1 < array.items.len() {
+                self.write(",");
+            }
+        }
+        self.write_newline(indent_level);
+        self.write("]");
+    }
+
+    /// Writes an array of scalars with as many items per line as fit in the
+    /// line width, or [DEFAULT_FILL_WIDTH] if there is none.
+    fn write_filled(&mut self, array: &JSONArray, indent_level: usize) {
+        let max_line_width = self.max_line_width.unwrap_or(DEFAULT_FILL_WIDTH);
+        self.write("[");
+        self.write_newline(indent_level + 1);
+        let line_start = self.column;
+        for (index, item) in array.items.iter().enumerate() {
+            let text = item.to_compact_string();
+            let is_last = index + 1 == array.items.len();
+            // the item and its comma, after a space.
+            let width = text.chars().count() + usize::from(!is_last);
+            if self.column > line_start {
+                if self.column + 1 + width > max_line_width {
+                    self.write_newline(indent_level + 1);
+                } else {
+                    self.write(" ");
+                }
+            }
+            self.write(&text);
+            if !is_last {
+                self.write(",");
+            }
+        }
+        self.write_newline(indent_level);
+        self.write("]");
+    }
+
+    /// Writes a node on a single line.
+    fn write_flat(&mut self, node: &JSONNode) {
+        self.write(&node.to_compact_string());
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+        self.column += text.chars().count();
+    }
+
+    /// Starts a new line for the item at `index` of an array or object,
+    /// leaving a blank line before it if the source had one.
+    fn write_item_newline(&mut self, index: usize, offset: usize, indent_level: usize) {
+        if index > 0 && self.blank_lines.binary_search(&offset).is_ok() {
+            self.output.push_str(self.newline);
+        }
+        self.write_newline(indent_level);
+    }
+
+    fn write_newline(&mut self, indent_level: usize) {
+        self.output.push_str(self.newline);
+        self.column = 0;
+        for _ in 0..indent_level {
+            self.write(INDENT);
+        }
+    }
+}
+
+/// Whether the node is a string, number, boolean or null.
+fn is_scalar(node: &JSONNode) -> bool {
+    !matches!(node, JSONNode::Array(_) | JSONNode::Object(_))
+}
+
+/// Whether the array only holds arrays of numbers, like a matrix.
+fn is_matrix(array: &JSONArray) -> bool {
+    array.items.iter().all(|row| match row {
+        JSONNode::Array(row) => row
+            .items
+            .iter()
+            .all(|item| matches!(item, JSONNode::Number(_))),
+        _ => false,
+    })
+}
+
+/// Returns the objects of the array, if it only holds objects that have the
+/// same keys in the same order.
+fn table_rows(array: &JSONArray) -> Option<Vec<&JSONObject>> {
+    let rows: Vec<&JSONObject> = array
+        .items
+        .iter()
+        .map(|item| match item {
+            JSONNode::Object(object) => Some(object),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    let first = rows.first()?;
+    let same_keys = |row: &&JSONObject| {
+        row.members.len() == first.members.len()
+            && row
+                .members
+                .iter()
+                .zip(&first.members)
+                .all(|(a, b)| a.key.value == b.key.value)
+    };
+    (!first.members.is_empty() && rows.iter().all(same_keys)).then_some(rows)
+}
+
+/// Returns the width of the node on a single line, or `None` if it is wider
+/// than `limit`. Stops measuring as soon as it is too wide.
+fn flat_width(node: &JSONNode, limit: usize) -> Option<usize> {
+    let width = match node {
+        JSONNode::Bool(_) | JSONNode::Null(_) => node.to_compact_string().len(),
+        JSONNode::Number(number) => number.raw.len(),
+        JSONNode::String(string) => string.raw.chars().count(),
+        JSONNode::Array(array) => {
+            // brackets, and a comma and space between items.
+            let mut width = 2 + 2 * array.items.len().saturating_sub(1);
+            for item in &array.items {
+                width += flat_width(item, limit.checked_sub(width)?)?;
+            }
+            width
+        }
+        JSONNode::Object(object) => {
+            // braces, a comma and space between members, and `: ` after keys.
+            let mut width = 2 + 2 * object.members.len().saturating_sub(1);
+            for member in &object.members {
+                width += member.key.raw.chars().count() + 2;
+                width += flat_width(&member.value, limit.checked_sub(width)?)?;
+            }
+            width
+        }
+    };
+    (width <= limit).then_some(width)
+}
@@ -0,0 +1,101 @@
+//! Checks that documents conform to [I-JSON] (RFC 7493), the profile of JSON
+//! meant for protocols and APIs.
+//!
+//! I-JSON forbids duplicate keys, numbers that a double can't hold exactly,
+//! and strings with lone surrogates or noncharacters. It also recommends the
+//! top-level value be an object or an array, which is only checked when
+//! asked to.
+//!
+//! Duplicate keys are found by the [duplicate_keys][crate::duplicate_keys]
+//! module, which the profile makes errors.
+//!
+//! [I-JSON]: https://www.rfc-editor.org/rfc/rfc7493
+use std::str::FromStr;
+
+use crate::{
+    errors::{ErrorCode, Issue},
+    nodes::{JSONFile, JSONNode, JSONString},
+    numbers,
+    strings::has_lone_surrogate,
+};
+
+/// The I-JSON profile, as an option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IJson {
+    /// Whether the top-level value must be an object or an array.
+    pub require_container: bool,
+}
+
+impl FromStr for IJson {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "i-json" => Ok(IJson::default()),
+            _ => Err(format!("Invalid profile {:?}, expected i-json", value)),
+        }
+    }
+}
+
+impl IJson {
+    /// Returns every value in the file outside of I-JSON, in source order,
+    /// except for duplicate keys.
+    pub fn check(&self, file: &JSONFile) -> Vec<Issue> {
+        let mut violations = vec![];
+        if self.require_container && !matches!(file.value, JSONNode::Array(_) | JSONNode::Object(_))
+        {
+            violations.push(Issue::new(
+                file.value.span(),
+                ErrorCode::ScalarTopLevel,
+                "The top-level value is not an object or an array".to_string(),
+            ));
+        }
+        check_node(&file.value, &mut violations);
+        violations
+    }
+}
+
+fn check_node(node: &JSONNode, violations: &mut Vec<Issue>) {
+    match node {
+        JSONNode::Number(number) => {
+            violations.extend(numbers::check(number));
+        }
+        JSONNode::String(string) => check_string(string, violations),
+        JSONNode::Array(array) => {
+            for item in &array.items {
+                check_node(item, violations);
+            }
+        }
+        JSONNode::Object(object) => {
+            for member in &object.members {
+                check_string(&member.key, violations);
+                check_node(&member.value, violations);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_string(string: &JSONString, violations: &mut Vec<Issue>) {
+    if has_lone_surrogate(&string.raw) {
+        violations.push(Issue::new(
+            string.span.clone(),
+            ErrorCode::LoneSurrogate,
+            "String contains a lone surrogate".to_string(),
+        ));
+    }
+    if let Some(char) = string.value.chars().find(|&char| is_noncharacter(char)) {
+        violations.push(Issue::new(
+            string.span.clone(),
+            ErrorCode::Noncharacter,
+            format!("String contains the noncharacter U+{:04X}", char as u32),
+        ));
+    }
+}
+
+/// Whether the character is one of the 66 permanently reserved noncharacters,
+/// U+FDD0 to U+FDEF and the last two code points of every plane.
+fn is_noncharacter(char: char) -> bool {
+    let code = char as u32;
+    (0xFDD0..=0xFDEF).contains(&code) || code & 0xFFFE == 0xFFFE
+}
@@ -0,0 +1,375 @@
+//! Repairs common mistakes in almost-JSON text, such as the output of
+//! hand-written configs or language models.
+//!
+//! The repairs are done in two passes:
+//!
+//! - A lexical pass fixes things the [tokenizer][crate::tokenizer] can't
+//!   read: single quoted strings, unquoted keys, Python's `True`, `False` and
+//!   `None`, whitespace JSON doesn't allow, and stray text after the JSON value.
+//! - A structural pass runs the [validator][crate::validator] and fixes the
+//!   errors it finds: missing commas, colons and values, trailing commas, and
+//!   brackets left unclosed at the end of the file.
+//!
+//! Every repair is reported as a warning diagnostic, pointing at the original
+//! source.
+use std::ops::Range;
+
+use crate::{
+    errors::{ErrorCode, FormatJsonError, InvalidSyntaxDiagnostic},
+    tokenizer::{self, Token, TokenType},
+    validator::{self, ValidationError},
+};
+
+/// The structural pass stops after this many rounds, in case the fixes of
+/// one round keep uncovering new errors.
+const MAX_ROUNDS: usize = 10;
+
+/// A single repair: replaces a range of the original source.
+#[derive(Debug, Clone)]
+pub struct Fix {
+    /// The range in the original source that is replaced.
+    pub span: Range<usize>,
+    pub replacement: String,
+    pub code: ErrorCode,
+    /// Describes the repair, e.g. "Inserted missing comma".
+    pub message: String,
+}
+
+/// The repaired source, along with the fixes that were applied to it.
+#[derive(Debug)]
+pub struct Repaired {
+    pub source: String,
+    pub fixes: Vec<Fix>,
+}
+
+impl Repaired {
+    /// Returns a warning diagnostic for each fix, pointing at the original source.
+    pub fn diagnostics(&self, filepath: &str, original: &str) -> Vec<InvalidSyntaxDiagnostic> {
+        let warning = |diagnostic: InvalidSyntaxDiagnostic, fix: &Fix| {
+            diagnostic
+                .with_code(fix.code)
+                .with_severity(miette::Severity::Warning)
+        };
+        let mut fixes = self.fixes.iter();
+        let Some(first_fix) = fixes.next() else {
+            return vec![];
+        };
+        let first = InvalidSyntaxDiagnostic::new(
+            filepath,
+            original,
+            first_fix.span.clone().into(),
+            first_fix.message.clone(),
+        );
+        // the rest share the source with the first one.
+        let others: Vec<_> = fixes
+            .map(|fix| {
+                warning(
+                    first.sibling(fix.span.clone().into(), fix.message.clone()),
+                    fix,
+                )
+            })
+            .collect();
+        let mut diagnostics = vec![warning(first, first_fix)];
+        diagnostics.extend(others);
+        diagnostics
+    }
+
+    /// Maps an offset in the repaired source back to the original source.
+    pub fn original_offset(&self, offset: usize) -> usize {
+        original_offset(&self.fixes, offset)
+    }
+}
+
+/// Repairs the source, returning the repaired source and the fixes applied.
+///
+/// Throws a [FormatJsonError] if the source has errors that can't be repaired.
+pub fn repair(source: &str, filepath: &str) -> Result<Repaired, FormatJsonError> {
+    let mut fixes = lexical_fixes(source);
+
+    for _ in 0..MAX_ROUNDS {
+        let current = apply(source, &fixes);
+        let tokens = tokenizer::tokenize(&current, filepath.to_string())?;
+        let errors = validator::validate_all(&tokens, usize::MAX);
+        if errors.is_empty() {
+            return Ok(Repaired {
+                source: current,
+                fixes,
+            });
+        }
+
+        let mut new_fixes = vec![];
+        for error in &errors {
+            match structural_fix(error, &tokens, &current) {
+                Some(fix) => new_fixes.push(fix),
+                None => {
+                    let diagnostic =
+                        validator::errors_to_diagnostic(&errors, filepath, &current, &tokens);
+                    return Err(diagnostic.expect("There is at least one error").into());
+                }
+            }
+        }
+        // The fixes point at the current source: map them back to the original.
+        for fix in &mut new_fixes {
+            fix.span =
+                original_offset(&fixes, fix.span.start)..original_offset(&fixes, fix.span.end);
+        }
+        fixes.extend(new_fixes);
+        sort_fixes(&mut fixes);
+    }
+
+    let current = apply(source, &fixes);
+    let tokens = tokenizer::tokenize(&current, filepath.to_string())?;
+    let errors = validator::validate_all(&tokens, usize::MAX);
+    match validator::errors_to_diagnostic(&errors, filepath, &current, &tokens) {
+        Some(diagnostic) => Err(diagnostic.into()),
+        None => Ok(Repaired {
+            source: current,
+            fixes,
+        }),
+    }
+}
+
+/// Orders fixes by where they start. Insertions come before replacements
+/// starting at the same place, and otherwise the order is kept.
+fn sort_fixes(fixes: &mut [Fix]) {
+    fixes.sort_by_key(|fix| (fix.span.start, !fix.span.is_empty()));
+}
+
+/// Applies the fixes, which must be sorted and not overlap, to the source.
+fn apply(source: &str, fixes: &[Fix]) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut last_end = 0;
+    for fix in fixes {
+        output.push_str(&source[last_end..fix.span.start]);
+        output.push_str(&fix.replacement);
+        last_end = fix.span.end;
+    }
+    output.push_str(&source[last_end..]);
+    output
+}
+
+/// Maps an offset in the source with the fixes applied, back to the original.
+fn original_offset(fixes: &[Fix], offset: usize) -> usize {
+    // How far ahead the fixed source is from the original, so far.
+    let mut delta: isize = 0;
+    for fix in fixes {
+        let start = (fix.span.start as isize + delta) as usize;
+        if offset < start {
+            break;
+        }
+        if offset < start + fix.replacement.len() {
+            // inside the replacement: point at the start of what it replaced.
+            return fix.span.start;
+        }
+        delta += fix.replacement.len() as isize - fix.span.len() as isize;
+    }
+    (offset as isize - delta) as usize
+}
+
+/// Finds the fixes the tokenizer needs, in a single pass over the source.
+fn lexical_fixes(source: &str) -> Vec<Fix> {
+    let mut fixes = vec![];
+    let mut chars = source.char_indices().peekable();
+    let mut depth = 0usize;
+    // Whether a complete value has been seen at the top level.
+    let mut seen_value = false;
+
+    while let Some((index, char)) = chars.next() {
+        // the tokenizer only skips JSON's own whitespace, and a leading byte order mark.
+        if " \n\t\r".contains(char) || (char == tokenizer::BOM && index == 0) {
+            continue;
+        }
+        if char.is_whitespace() {
+            fixes.push(Fix {
+                span: index..index + char.len_utf8(),
+                replacement: " ".to_string(),
+                code: ErrorCode::NonAsciiWhitespace,
+                message: format!(
+                    "Replaced the whitespace character U+{:04X} with a space",
+                    char as u32
+                ),
+            });
+            continue;
+        }
+        if depth == 0 && seen_value {
+            fixes.push(Fix {
+                span: index..source.len(),
+                replacement: String::new(),
+                code: ErrorCode::TrailingText,
+                message: "Removed text after the JSON value".to_string(),
+            });
+            break;
+        }
+
+        match char {
+            '"' | '\'' => {
+                let Some((end, contents)) = scan_string(source, index, char) else {
+                    // unterminated: leave it for the tokenizer to report.
+                    break;
+                };
+                while chars.next_if(|&(i, _)| i < end).is_some() {}
+                if char == '\'' {
+                    fixes.push(Fix {
+                        span: index..end,
+                        replacement: format!("\"{}\"", contents),
+                        code: ErrorCode::SingleQuotedString,
+                        message: "Replaced single quotes with double quotes".to_string(),
+                    });
+                }
+                seen_value |= depth == 0;
+            }
+            '[' | '{' => depth += 1,
+            ']' | '}' => {
+                depth = depth.saturating_sub(1);
+                seen_value |= depth == 0;
+            }
+            _ if char.is_alphabetic() || char == '_' || char == '$' => {
+                let mut end = index + char.len_utf8();
+                while let Some((i, next)) =
+                    chars.next_if(|&(_, next)| next.is_alphanumeric() || next == '_' || next == '$')
+                {
+                    end = i + next.len_utf8();
+                }
+                let word = &source[index..end];
+                let followed_by_colon = source[end..].trim_start().starts_with(':');
+                let fix = match word {
+                    "True" | "False" | "None" if !followed_by_colon => Some((
+                        if word == "None" {
+                            "null".to_string()
+                        } else {
+                            word.to_lowercase()
+                        },
+                        ErrorCode::PythonLiteral,
+                        format!("Replaced Python's {} with its JSON equivalent", word),
+                    )),
+                    _ if followed_by_colon && depth > 0 => Some((
+                        format!("\"{}\"", word),
+                        ErrorCode::UnquotedKey,
+                        "Added quotes around the key".to_string(),
+                    )),
+                    _ => None,
+                };
+                if let Some((replacement, code, message)) = fix {
+                    fixes.push(Fix {
+                        span: index..end,
+                        replacement,
+                        code,
+                        message,
+                    });
+                }
+                seen_value |= depth == 0;
+            }
+            '0'..='9' | '-' => {
+                while chars
+                    .next_if(|&(_, next)| matches!(next, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                    .is_some()
+                {}
+                seen_value |= depth == 0;
+            }
+            _ => {}
+        }
+    }
+    fixes
+}
+
+/// Reads a string starting with the given quote character at `index`.
+///
+/// Returns the offset just past the closing quote, and the contents of the
+/// string escaped for use inside double quotes.
+fn scan_string(source: &str, index: usize, quote: char) -> Option<(usize, String)> {
+    let mut contents = String::new();
+    let mut chars = source[index + 1..].char_indices();
+    while let Some((i, char)) = chars.next() {
+        match char {
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                // `\'` is not a valid JSON escape, but a quote needs none.
+                if escaped != '\'' {
+                    contents.push('\\');
+                }
+                contents.push(escaped);
+            }
+            '"' if quote == '\'' => contents.push_str("\\\""),
+            _ if char == quote => return Some((index + 1 + i + 1, contents)),
+            _ => contents.push(char),
+        }
+    }
+    None
+}
+
+/// Returns the fix for an error found by the validator, if there is one.
+fn structural_fix(error: &ValidationError, tokens: &[Token], source: &str) -> Option<Fix> {
+    // Insertions go right after the token before the error.
+    let end_of_previous = |offset: usize| {
+        let index = tokens.partition_point(|token| token.byte_offset < offset);
+        tokens[..index].last().map_or(0, |token| token.end_offset)
+    };
+    let insert = |offset: usize, text: &str, message: &str| Fix {
+        span: offset..offset,
+        replacement: text.to_string(),
+        code: error.code(),
+        message: message.to_string(),
+    };
+
+    match error {
+        ValidationError::MissingComma(offset, _) => Some(insert(
+            end_of_previous(*offset),
+            ",",
+            "Inserted missing comma",
+        )),
+        ValidationError::TrailingComma(offset) => Some(Fix {
+            span: *offset..*offset + 1,
+            replacement: String::new(),
+            code: error.code(),
+            message: "Removed trailing comma".to_string(),
+        }),
+        ValidationError::MissingColon(offset, _) => {
+            let token = tokens.iter().find(|token| token.byte_offset == *offset)?;
+            let starts_value = !matches!(
+                token.token_type,
+                TokenType::Comma | TokenType::RightBrace | TokenType::RightBracket
+            );
+            Some(if starts_value {
+                insert(end_of_previous(*offset), ":", "Inserted missing colon")
+            } else {
+                insert(end_of_previous(*offset), ": null", "Inserted missing value")
+            })
+        }
+        ValidationError::UnexpectedToken(offset, _) => {
+            // Only a value missing after a colon can be repaired.
+            let index = tokens.partition_point(|token| token.byte_offset < *offset);
+            let follows_colon = index > 0 && tokens[index - 1].token_type == TokenType::Colon;
+            let ends_value = matches!(
+                tokens.get(index).map(|token| &token.token_type),
+                Some(TokenType::Comma | TokenType::RightBrace)
+            );
+            (follows_colon && ends_value)
+                .then(|| insert(end_of_previous(*offset), " null", "Inserted missing value"))
+        }
+        ValidationError::UnexpectedEOF(_) => {
+            let mut closing = String::new();
+            match tokens.last().map(|token| &token.token_type) {
+                Some(TokenType::Colon) => closing.push_str("null"),
+                Some(TokenType::Comma) => {
+                    // A comma followed by the end of the file: drop the comma.
+                    let comma = tokens.last()?;
+                    return Some(Fix {
+                        span: comma.span(),
+                        replacement: String::new(),
+                        code: ErrorCode::TrailingComma,
+                        message: "Removed trailing comma".to_string(),
+                    });
+                }
+                _ => {}
+            }
+            for token in validator::unclosed_brackets(tokens).iter().rev() {
+                closing.push(match token.token_type {
+                    TokenType::LeftBracket => ']',
+                    _ => '}',
+                });
+            }
+            Some(insert(source.len(), &closing, "Closed brackets left open"))
+        }
+        _ => None,
+    }
+}
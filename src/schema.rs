@@ -0,0 +1,601 @@
+//! Validates documents against a [JSON Schema](https://json-schema.org/).
+//!
+//! The core keywords of drafts 2020-12 and 07 are supported:
+//!
+//! - `type`, `enum` and `const`,
+//! - `properties`, `patternProperties`, `additionalProperties`, `required`,
+//!   `minProperties` and `maxProperties` for objects,
+//! - `items`, `prefixItems` (`additionalItems` in draft-07), `minItems` and
+//!   `maxItems` for arrays,
+//! - `pattern`, `minLength` and `maxLength` for strings,
+//! - `minimum`, `maximum`, `exclusiveMinimum` and `exclusiveMaximum` for numbers,
+//! - `$ref`, to a JSON pointer within the schema (`#/$defs/name`) or to a
+//!   local file relative to the schema (`other.json#/$defs/name`).
+//!
+//! Other keywords are ignored.
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
+
+use regex_lite::Regex;
+
+use crate::{
+    errors::{ErrorCode, FormatJsonError, Issue},
+    nodes::{JSONArray, JSONFile, JSONNode, JSONObject},
+    parser,
+};
+
+/// Following more `$ref`s than this in a row, for the same value, means they
+/// form a cycle.
+const MAX_REF_DEPTH: usize = 64;
+
+/// Which schema to validate documents against.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SchemaSource {
+    /// Don't validate against a schema.
+    #[default]
+    None,
+    /// The local schema file named by the document's `$schema` key, if it
+    /// has one. The path is relative to the document.
+    Document,
+    /// The given schema file.
+    File(PathBuf),
+}
+
+impl FromStr for SchemaSource {
+    type Err = String;
+
+    /// Parses `none`, `auto` for [SchemaSource::Document], or a file path.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "" => Err("Expected a schema path, auto or none".to_string()),
+            "none" => Ok(SchemaSource::None),
+            "auto" => Ok(SchemaSource::Document),
+            _ => Ok(SchemaSource::File(PathBuf::from(value))),
+        }
+    }
+}
+
+impl SchemaSource {
+    /// Loads the schema for the document at `filepath`, if there is one.
+    ///
+    /// Throws a [FormatJsonError] if the schema file can't be read or parsed.
+    pub fn load(
+        &self,
+        document: &JSONFile,
+        filepath: &str,
+    ) -> Result<Option<Schema>, FormatJsonError> {
+        let path = match self {
+            SchemaSource::None => return Ok(None),
+            SchemaSource::Document => match document_schema_path(document, Path::new(filepath)) {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+            SchemaSource::File(path) => path.clone(),
+        };
+        Schema::from_file(&path).map(Some)
+    }
+}
+
+/// The schema drafts, which differ in how some keywords behave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Draft {
+    Draft7,
+    Draft2020,
+}
+
+/// A parsed schema file.
+struct Document {
+    file: JSONFile,
+    /// Path of the file, which relative `$ref`s are resolved against.
+    path: Option<PathBuf>,
+    draft: Draft,
+}
+
+impl Document {
+    fn new(file: JSONFile, path: Option<PathBuf>) -> Self {
+        let draft = match &file.value {
+            JSONNode::Object(object) => match object.get("$schema") {
+                Some(JSONNode::String(uri))
+                    if ["draft-04", "draft-06", "draft-07"]
+                        .iter()
+                        .any(|draft| uri.value.contains(draft)) =>
+                {
+                    Draft::Draft7
+                }
+                _ => Draft::Draft2020,
+            },
+            _ => Draft::Draft2020,
+        };
+        Self { file, path, draft }
+    }
+}
+
+/// A value that doesn't match the schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// Range of the offending value in the document.
+    pub span: Range<usize>,
+    /// JSON pointer to the offending value, e.g. `/servers/0/port`.
+    pub path: String,
+    /// The schema keyword that failed, e.g. `type`.
+    pub keyword: &'static str,
+    pub message: String,
+}
+
+impl SchemaError {
+    /// Returns the error as an [Issue], with its path in the message.
+    pub fn to_issue(&self) -> Issue {
+        let message = match self.path.as_str() {
+            "" => self.message.clone(),
+            path => format!("{} at {}", self.message, path),
+        };
+        Issue::new(self.span.clone(), ErrorCode::SchemaViolation, message)
+    }
+}
+
+/// A JSON Schema, ready to validate documents.
+pub struct Schema {
+    root: Rc<Document>,
+    /// Other schema files loaded through `$ref`, by path.
+    documents: RefCell<HashMap<PathBuf, Rc<Document>>>,
+    regexes: RefCell<HashMap<String, Regex>>,
+}
+
+impl Schema {
+    /// Reads and parses the schema file.
+    ///
+    /// Throws a [FormatJsonError] if the file can't be read, or is not valid JSON.
+    pub fn from_file(path: &Path) -> Result<Self, FormatJsonError> {
+        Ok(Self::new(load(path)?))
+    }
+
+    /// Parses the schema source. Relative `$ref`s are resolved against `path`,
+    /// or the current directory if there is none.
+    ///
+    /// Throws a [FormatJsonError] if the source is not valid JSON.
+    pub fn from_source(source: &str, path: Option<&Path>) -> Result<Self, FormatJsonError> {
+        let filepath = path.map_or("<schema>".into(), |path| path.to_string_lossy());
+        let file = parser::parse(source, &filepath)?;
+        Ok(Self::new(Document::new(file, path.map(Path::to_path_buf))))
+    }
+
+    fn new(root: Document) -> Self {
+        Self {
+            root: Rc::new(root),
+            documents: RefCell::new(HashMap::new()),
+            regexes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Validates the document, returning every place it doesn't match the schema.
+    ///
+    /// Throws a [FormatJsonError] if the schema itself is broken, e.g. it has
+    /// a `$ref` to a file that doesn't exist.
+    pub fn validate(&self, document: &JSONFile) -> Result<Vec<SchemaError>, FormatJsonError> {
+        let mut validation = Validation {
+            schema: self,
+            errors: vec![],
+            path: String::new(),
+            ref_depth: 0,
+        };
+        validation.validate(&self.root.file.value, &self.root, &document.value)?;
+        Ok(validation.errors)
+    }
+
+    /// Returns the document at the path, loading it on first use.
+    fn document(&self, path: &Path) -> Result<Rc<Document>, FormatJsonError> {
+        if let Some(document) = self.documents.borrow().get(path) {
+            return Ok(document.clone());
+        }
+        let document = Rc::new(load(path)?);
+        self.documents
+            .borrow_mut()
+            .insert(path.to_path_buf(), document.clone());
+        Ok(document)
+    }
+
+    fn is_match(&self, pattern: &str, text: &str) -> Result<bool, FormatJsonError> {
+        let mut regexes = self.regexes.borrow_mut();
+        if !regexes.contains_key(pattern) {
+            let regex = Regex::new(pattern).map_err(|err| {
+                FormatJsonError::InvalidSchema(format!("Invalid pattern {:?}: {}", pattern, err))
+            })?;
+            regexes.insert(pattern.to_string(), regex);
+        }
+        Ok(regexes[pattern].is_match(text))
+    }
+}
+
+fn load(path: &Path) -> Result<Document, FormatJsonError> {
+    let source = fs::read_to_string(path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => {
+            FormatJsonError::FileNotFound(path.to_string_lossy().to_string())
+        }
+        _ => err.into(),
+    })?;
+    let file = parser::parse(&source, &path.to_string_lossy())?;
+    Ok(Document::new(file, Some(path.to_path_buf())))
+}
+
+/// The state of a single [Schema::validate] call.
+struct Validation<'s> {
+    schema: &'s Schema,
+    errors: Vec<SchemaError>,
+    /// JSON pointer to the value being validated.
+    path: String,
+    /// The number of `$ref`s followed to get to the current schema, without
+    /// moving to a child value.
+    ref_depth: usize,
+}
+
+impl Validation<'_> {
+    fn error(&mut self, value: &JSONNode, keyword: &'static str, message: String) {
+        let span = value.span();
+        // point at the opening bracket of arrays and objects, not all of them.
+        let span = match value {
+            JSONNode::Array(_) | JSONNode::Object(_) => span.start..span.start + 1,
+            _ => span,
+        };
+        self.error_at(span, keyword, message);
+    }
+
+    fn error_at(&mut self, span: Range<usize>, keyword: &'static str, message: String) {
+        self.errors.push(SchemaError {
+            span,
+            path: self.path.clone(),
+            keyword,
+            message,
+        });
+    }
+
+    /// Validates a value at `path` within the value, e.g. an array item.
+    fn validate_child(
+        &mut self,
+        schema: &JSONNode,
+        document: &Rc<Document>,
+        value: &JSONNode,
+        path: &str,
+    ) -> Result<(), FormatJsonError> {
+        let length = self.path.len();
+        self.path.push('/');
+        self.path
+            .push_str(&path.replace('~', "~0").replace('/', "~1"));
+        // only `$ref`s followed for the same value can form a cycle.
+        let ref_depth = std::mem::take(&mut self.ref_depth);
+        let result = self.validate(schema, document, value);
+        self.ref_depth = ref_depth;
+        self.path.truncate(length);
+        result
+    }
+
+    fn validate(
+        &mut self,
+        schema: &JSONNode,
+        document: &Rc<Document>,
+        value: &JSONNode,
+    ) -> Result<(), FormatJsonError> {
+        let schema = match schema {
+            JSONNode::Bool(allowed) => {
+                if !allowed.value {
+                    self.error(value, "false", "No value is allowed here".to_string());
+                }
+                return Ok(());
+            }
+            JSONNode::Object(schema) => schema,
+            _ => {
+                return Err(FormatJsonError::InvalidSchema(format!(
+                    "Expected a schema object, found {}",
+                    schema.to_compact_string()
+                )))
+            }
+        };
+
+        if let Some(reference) = schema.get("$ref") {
+            self.validate_ref(reference, document, value)?;
+            // before 2019-09, `$ref` replaces the other keywords.
+            if document.draft == Draft::Draft7 {
+                return Ok(());
+            }
+        }
+
+        self.validate_type(schema, value);
+        if let Some(JSONNode::Array(options)) = schema.get("enum") {
+            if !options.items.iter().any(|option| option.equals(value)) {
+                let options: Vec<_> = options
+                    .items
+                    .iter()
+                    .map(|o| o.to_compact_string())
+                    .collect();
+                self.error(
+                    value,
+                    "enum",
+                    format!("Expected one of {}", options.join(", ")),
+                );
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if !constant.equals(value) {
+                self.error(
+                    value,
+                    "const",
+                    format!("Expected {}", constant.to_compact_string()),
+                );
+            }
+        }
+
+        match value {
+            JSONNode::Object(object) => self.validate_object(schema, document, value, object)?,
+            JSONNode::Array(array) => self.validate_array(schema, document, value, array)?,
+            JSONNode::String(string) => {
+                let length = string.value.chars().count() as f64;
+                if let Some(min) = number(schema, "minLength").filter(|min| length < min.0) {
+                    let message = format!("Expected at least {} characters", min.1);
+                    self.error(value, "minLength", message);
+                }
+                if let Some(max) = number(schema, "maxLength").filter(|max| length > max.0) {
+                    let message = format!("Expected at most {} characters", max.1);
+                    self.error(value, "maxLength", message);
+                }
+                if let Some(JSONNode::String(pattern)) = schema.get("pattern") {
+                    if !self.schema.is_match(&pattern.value, &string.value)? {
+                        let message = format!("Expected to match the pattern {}", pattern.raw);
+                        self.error(value, "pattern", message);
+                    }
+                }
+            }
+            JSONNode::Number(number) => self.validate_number(schema, value, number.value),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn validate_ref(
+        &mut self,
+        reference: &JSONNode,
+        document: &Rc<Document>,
+        value: &JSONNode,
+    ) -> Result<(), FormatJsonError> {
+        let JSONNode::String(reference) = reference else {
+            return Err(FormatJsonError::InvalidSchema(
+                "$ref must be a string".to_string(),
+            ));
+        };
+        if self.ref_depth >= MAX_REF_DEPTH {
+            return Err(FormatJsonError::InvalidSchema(format!(
+                "Too many nested $refs, at {}",
+                reference.raw
+            )));
+        }
+
+        let (file, pointer) = reference
+            .value
+            .split_once('#')
+            .unwrap_or((&reference.value, ""));
+        let target_document = if file.is_empty() {
+            document.clone()
+        } else {
+            if file.contains("://") {
+                return Err(FormatJsonError::InvalidSchema(format!(
+                    "Only local files can be referenced, found {}",
+                    reference.raw
+                )));
+            }
+            let base = document
+                .path
+                .as_ref()
+                .and_then(|path| path.parent())
+                .unwrap_or(Path::new(""));
+            self.schema.document(&base.join(file))?
+        };
+        let target = resolve_pointer(&target_document.file.value, pointer).ok_or_else(|| {
+            FormatJsonError::InvalidSchema(format!("Can't resolve $ref {}", reference.raw))
+        })?;
+
+        self.ref_depth += 1;
+        let result = self.validate(target, &target_document, value);
+        self.ref_depth -= 1;
+        result
+    }
+
+    fn validate_type(&mut self, schema: &JSONObject, value: &JSONNode) {
+        let types: Vec<&str> = match schema.get("type") {
+            Some(JSONNode::String(name)) => vec![&name.value],
+            Some(JSONNode::Array(names)) => names
+                .items
+                .iter()
+                .filter_map(|name| match name {
+                    JSONNode::String(name) => Some(name.value.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => return,
+        };
+        let matches = |name: &str| match (name, value) {
+            ("integer", JSONNode::Number(number)) => number.value.fract() == 0.0,
+            _ => name == value.type_name(),
+        };
+        if !types.iter().any(|name| matches(name)) {
+            let message = format!(
+                "Expected {}, found {}",
+                types.join(" or "),
+                value.type_name()
+            );
+            self.error(value, "type", message);
+        }
+    }
+
+    fn validate_object(
+        &mut self,
+        schema: &JSONObject,
+        document: &Rc<Document>,
+        value: &JSONNode,
+        object: &JSONObject,
+    ) -> Result<(), FormatJsonError> {
+        if let Some(JSONNode::Array(required)) = schema.get("required") {
+            for key in &required.items {
+                if let JSONNode::String(key) = key {
+                    if object.get(&key.value).is_none() {
+                        let message = format!("Missing required property {}", key.raw);
+                        self.error(value, "required", message);
+                    }
+                }
+            }
+        }
+        let count = object.members.len() as f64;
+        if let Some(min) = number(schema, "minProperties").filter(|min| count < min.0) {
+            let message = format!("Expected at least {} properties", min.1);
+            self.error(value, "minProperties", message);
+        }
+        if let Some(max) = number(schema, "maxProperties").filter(|max| count > max.0) {
+            let message = format!("Expected at most {} properties", max.1);
+            self.error(value, "maxProperties", message);
+        }
+
+        let properties = match schema.get("properties") {
+            Some(JSONNode::Object(properties)) => Some(properties),
+            _ => None,
+        };
+        let pattern_properties = match schema.get("patternProperties") {
+            Some(JSONNode::Object(patterns)) => &patterns.members[..],
+            _ => &[],
+        };
+        let additional = schema.get("additionalProperties");
+        for member in &object.members {
+            let key = &member.key.value;
+            let mut matched = false;
+            if let Some(property) = properties.and_then(|properties| properties.get(key)) {
+                matched = true;
+                self.validate_child(property, document, &member.value, key)?;
+            }
+            for pattern in pattern_properties {
+                if self.schema.is_match(&pattern.key.value, key)? {
+                    matched = true;
+                    self.validate_child(&pattern.value, document, &member.value, key)?;
+                }
+            }
+            match additional {
+                Some(JSONNode::Bool(allowed)) if !matched && !allowed.value => {
+                    let message = format!("Property {} is not allowed", member.key.raw);
+                    self.error_at(member.key.span.clone(), "additionalProperties", message);
+                }
+                Some(additional) if !matched => {
+                    self.validate_child(additional, document, &member.value, key)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_array(
+        &mut self,
+        schema: &JSONObject,
+        document: &Rc<Document>,
+        value: &JSONNode,
+        array: &JSONArray,
+    ) -> Result<(), FormatJsonError> {
+        let count = array.items.len() as f64;
+        if let Some(min) = number(schema, "minItems").filter(|min| count < min.0) {
+            let message = format!("Expected at least {} items", min.1);
+            self.error(value, "minItems", message);
+        }
+        if let Some(max) = number(schema, "maxItems").filter(|max| count > max.0) {
+            let message = format!("Expected at most {} items", max.1);
+            self.error(value, "maxItems", message);
+        }
+
+        // the schemas for the first few items, and for the rest of them.
+        let (prefix, rest) = match (document.draft, schema.get("items")) {
+            (Draft::Draft7, Some(JSONNode::Array(prefix))) => {
+                (&prefix.items[..], schema.get("additionalItems"))
+            }
+            (Draft::Draft7, items) => (&[][..], items),
+            (Draft::Draft2020, items) => match schema.get("prefixItems") {
+                Some(JSONNode::Array(prefix)) => (&prefix.items[..], items),
+                _ => (&[][..], items),
+            },
+        };
+        for (index, item) in array.items.iter().enumerate() {
+            let item_schema = prefix.get(index).or(rest);
+            if let Some(item_schema) = item_schema {
+                self.validate_child(item_schema, document, item, &index.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_number(&mut self, schema: &JSONObject, value: &JSONNode, number_value: f64) {
+        for (keyword, operator) in [
+            ("minimum", ">="),
+            ("maximum", "<="),
+            ("exclusiveMinimum", ">"),
+            ("exclusiveMaximum", "<"),
+        ] {
+            let Some((limit, raw)) = number(schema, keyword) else {
+                continue;
+            };
+            let valid = match operator {
+                ">=" => number_value >= limit,
+                "<=" => number_value <= limit,
+                ">" => number_value > limit,
+                _ => number_value < limit,
+            };
+            if !valid {
+                let message = format!("Expected a number {} {}", operator, raw);
+                self.error(value, keyword, message);
+            }
+        }
+    }
+}
+
+/// Returns the numeric keyword of the schema, and how it's written.
+fn number<'a>(schema: &'a JSONObject, keyword: &str) -> Option<(f64, &'a str)> {
+    match schema.get(keyword) {
+        Some(JSONNode::Number(number)) => Some((number.value, &number.raw)),
+        _ => None,
+    }
+}
+
+/// Finds the value a JSON pointer such as `/$defs/name` points at.
+fn resolve_pointer<'a>(root: &'a JSONNode, pointer: &str) -> Option<&'a JSONNode> {
+    if pointer.is_empty() {
+        return Some(root);
+    }
+    let mut node = root;
+    for part in pointer.strip_prefix('/')?.split('/') {
+        let part = part.replace("~1", "/").replace("~0", "~");
+        node = match node {
+            JSONNode::Object(object) => object.get(&part)?,
+            JSONNode::Array(array) => array.items.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(node)
+}
+
+/// Returns the path of the local schema named by the document's `$schema`
+/// key, relative to the document at `filepath`.
+///
+/// Returns `None` if there is no `$schema` key, or it names a remote schema.
+pub fn document_schema_path(document: &JSONFile, filepath: &Path) -> Option<PathBuf> {
+    let JSONNode::Object(object) = &document.value else {
+        return None;
+    };
+    let Some(JSONNode::String(uri)) = object.get("$schema") else {
+        return None;
+    };
+    let path = match uri.value.strip_prefix("file://") {
+        Some(path) => path,
+        None if uri.value.contains("://") => return None,
+        None => &uri.value,
+    };
+    let base = filepath.parent().unwrap_or(Path::new(""));
+    Some(base.join(path))
+}
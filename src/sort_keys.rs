@@ -0,0 +1,273 @@
+//! Sorts the members of objects by their keys.
+use std::{cmp::Ordering, str::FromStr};
+
+use crate::nodes::{JSONFile, JSONNode};
+
+/// How keys are compared when sorting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// By Unicode code point, so `B` comes before `a`.
+    #[default]
+    Lexicographic,
+    /// Like [KeyOrder::Lexicographic], but numbers in keys are compared by
+    /// value, so `item2` comes before `item10`.
+    Natural,
+    /// Ignoring case, so `a` comes before `B`.
+    CaseInsensitive,
+}
+
+impl FromStr for KeyOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "lexicographic" => Ok(KeyOrder::Lexicographic),
+            "natural" => Ok(KeyOrder::Natural),
+            "case-insensitive" => Ok(KeyOrder::CaseInsensitive),
+            _ => Err(format!(
+                "Invalid key order {:?}, expected lexicographic, natural or case-insensitive",
+                value
+            )),
+        }
+    }
+}
+
+impl KeyOrder {
+    /// Compares two keys. Keys that only differ in ways the order ignores are
+    /// compared lexicographically, so the result is always deterministic.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = match self {
+            KeyOrder::Lexicographic => Ordering::Equal,
+            KeyOrder::Natural => compare_natural(a, b),
+            KeyOrder::CaseInsensitive => a.to_lowercase().cmp(&b.to_lowercase()),
+        };
+        ordering.then_with(|| a.cmp(b))
+    }
+}
+
+/// Compares runs of digits by their value, and everything else by code point.
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(a_char), Some(b_char)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let ordering = if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+            let a_end = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let b_end = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let a_digits = a[..a_end].trim_start_matches('0');
+            let b_digits = b[..b_end].trim_start_matches('0');
+            let ordering = a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(b_digits));
+            (a, b) = (&a[a_end..], &b[b_end..]);
+            ordering
+        } else {
+            (a, b) = (&a[a_char.len_utf8()..], &b[b_char.len_utf8()..]);
+            a_char.cmp(&b_char)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Which objects to sort, and how.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SortKeys {
+    pub order: KeyOrder,
+    /// Nesting depths of the objects to sort, where the top-level value is at
+    /// depth 0 and every array or object adds one. `None` sorts every object.
+    pub depths: Option<Vec<usize>>,
+}
+
+impl SortKeys {
+    /// Sorts the members of the objects in the file. Members with the same
+    /// key keep their order.
+    pub fn sort(&self, file: &mut JSONFile) {
+        self.sort_node(&mut file.value, 0);
+    }
+
+    fn sort_node(&self, node: &mut JSONNode, depth: usize) {
+        match node {
+            JSONNode::Array(array) => {
+                for item in &mut array.items {
+                    self.sort_node(item, depth + 1);
+                }
+            }
+            JSONNode::Object(object) => {
+                if self
+                    .depths
+                    .as_ref()
+                    .is_none_or(|depths| depths.contains(&depth))
+                {
+                    object
+                        .members
+                        .sort_by(|a, b| self.order.compare(&a.key.value, &b.key.value));
+                }
+                for member in &mut object.members {
+                    self.sort_node(&mut member.value, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Keys that go first, in the given order, in the top-level object of files
+/// matching a glob. The other keys follow in their original order, or sorted
+/// if [SortKeys] is also set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOrderRule {
+    /// Matched against the file name, or against the whole path if the glob
+    /// contains a `/`. Supports `*`, `**` and `?`.
+    pub glob: String,
+    pub keys: Vec<String>,
+}
+
+impl FromStr for KeyOrderRule {
+    type Err = String;
+
+    /// Parses a rule written as `glob=key,key,...`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some((glob, keys)) = value.split_once('=') else {
+            return Err(format!(
+                "Invalid key order rule {:?}, expected glob=key,key,...",
+                value
+            ));
+        };
+        Ok(KeyOrderRule::new(glob, keys.split(',').map(str::trim)))
+    }
+}
+
+impl KeyOrderRule {
+    pub fn new<'a>(glob: &str, keys: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            glob: glob.to_string(),
+            keys: keys.into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    /// Built-in rules for well-known files.
+    pub fn presets() -> Vec<Self> {
+        vec![
+            KeyOrderRule::new(
+                "package.json",
+                [
+                    "$schema",
+                    "name",
+                    "version",
+                    "private",
+                    "description",
+                    "keywords",
+                    "homepage",
+                    "bugs",
+                    "repository",
+                    "funding",
+                    "license",
+                    "author",
+                    "contributors",
+                    "type",
+                    "main",
+                    "module",
+                    "types",
+                    "exports",
+                    "bin",
+                    "files",
+                    "workspaces",
+                    "scripts",
+                    "dependencies",
+                    "devDependencies",
+                    "peerDependencies",
+                    "optionalDependencies",
+                    "engines",
+                ],
+            ),
+            KeyOrderRule::new(
+                "composer.json",
+                [
+                    "$schema",
+                    "name",
+                    "type",
+                    "description",
+                    "keywords",
+                    "homepage",
+                    "license",
+                    "authors",
+                    "support",
+                    "require",
+                    "require-dev",
+                    "suggest",
+                    "autoload",
+                    "autoload-dev",
+                    "config",
+                    "scripts",
+                    "extra",
+                    "minimum-stability",
+                    "prefer-stable",
+                ],
+            ),
+            KeyOrderRule::new(
+                "tsconfig*.json",
+                [
+                    "$schema",
+                    "extends",
+                    "compilerOptions",
+                    "include",
+                    "exclude",
+                    "files",
+                    "references",
+                ],
+            ),
+            KeyOrderRule::new(
+                "*.schema.json",
+                ["$schema", "$id", "title", "description", "type"],
+            ),
+        ]
+    }
+
+    /// Whether the rule applies to the file.
+    pub fn matches(&self, filepath: &str) -> bool {
+        let path = filepath.replace('\\', "/");
+        let path = match self.glob.contains('/') {
+            true => path.as_str(),
+            false => path.rsplit('/').next().unwrap_or_default(),
+        };
+        glob_matches(self.glob.as_bytes(), path.as_bytes())
+    }
+
+    /// Moves the rule's keys to the front of the top-level object.
+    pub fn apply(&self, file: &mut JSONFile) {
+        let JSONNode::Object(object) = &mut file.value else {
+            return;
+        };
+        let rank = |key: &str| {
+            self.keys
+                .iter()
+                .position(|k| k == key)
+                .unwrap_or(self.keys.len())
+        };
+        object.members.sort_by_key(|member| rank(&member.key.value));
+    }
+}
+
+/// Matches a path against a glob, where `*` and `?` don't match a `/`.
+fn glob_matches(glob: &[u8], path: &[u8]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        // `**/` matches any number of whole directories.
+        [b'*', b'*', b'/', rest @ ..] => (0..=path.len())
+            .filter(|&start| start == 0 || path[start - 1] == b'/')
+            .any(|start| glob_matches(rest, &path[start..])),
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|start| glob_matches(rest, &path[start..])),
+        [b'*', rest @ ..] => {
+            let end = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=end).any(|start| glob_matches(rest, &path[start..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(path, [c, ..] if *c != b'/') && glob_matches(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_matches(rest, &path[1..]),
+    }
+}
@@ -0,0 +1,291 @@
+//! Helpers for reading and writing JSON string literals.
+use std::str::FromStr;
+
+use crate::nodes::{JSONFile, JSONNode, JSONString};
+
+/// Returns the text as a quoted JSON string literal, escaping only what needs
+/// to be escaped: quotes, backslashes and control characters.
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for char in text.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\u{08}' => quoted.push_str("\\b"),
+            '\u{0C}' => quoted.push_str("\\f"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{00}'..='\u{1F}' => quoted.push_str(&format!("\\u{:04x}", char as u32)),
+            _ => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Returns the value of a JSON string literal, without quotes and with every
+/// escape sequence decoded.
+///
+/// Invalid escapes are kept as they are, and lone surrogates are replaced with
+/// U+FFFD, so this never fails.
+pub fn unquote(literal: &str) -> String {
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|inner| inner.strip_suffix('"'))
+        .unwrap_or(literal);
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            value.push(char);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => value.push('\u{08}'),
+            Some('f') => value.push('\u{0C}'),
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some('u') => {
+                let Some(unit) = hex_escape(&mut chars) else {
+                    value.push_str("\\u");
+                    continue;
+                };
+                let mut units = vec![unit];
+                // a high surrogate should be followed by an escaped low one.
+                if (0xD800..0xDC00).contains(&unit) && chars.as_str().starts_with("\\u") {
+                    let mut lookahead = chars.clone();
+                    lookahead.nth(1);
+                    if let Some(low @ 0xDC00..=0xDFFF) = hex_escape(&mut lookahead) {
+                        units.push(low);
+                        chars = lookahead;
+                    }
+                }
+                value.extend(char::decode_utf16(units).map(|c| c.unwrap_or('\u{FFFD}')));
+            }
+            Some(escaped) => value.push(escaped),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+/// Whether a JSON string literal escapes a surrogate that isn't part of a
+/// pair, which [unquote] replaces with U+FFFD.
+pub fn has_lone_surrogate(literal: &str) -> bool {
+    let mut chars = literal.chars();
+    // the low surrogate that must come next, after a high one.
+    let mut expect_low = false;
+    while let Some(char) = chars.next() {
+        let unit = match char {
+            '\\' => match chars.next() {
+                Some('u') => hex_escape(&mut chars),
+                _ => None,
+            },
+            _ => None,
+        };
+        match unit {
+            Some(0xDC00..=0xDFFF) if expect_low => expect_low = false,
+            Some(0xDC00..=0xDFFF) => return true,
+            _ if expect_low => return true,
+            Some(0xD800..=0xDBFF) => expect_low = true,
+            _ => {}
+        }
+    }
+    expect_low
+}
+
+/// Reads the four hex digits of a `\u` escape.
+fn hex_escape(chars: &mut std::str::Chars) -> Option<u16> {
+    let digits = chars.as_str().get(..4)?;
+    if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let unit = u16::from_str_radix(digits, 16).ok()?;
+    chars.nth(3);
+    Some(unit)
+}
+
+/// Which characters of strings are written as `\u` escapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscapeMode {
+    /// Keep escapes as they are written.
+    #[default]
+    Preserve,
+    /// Write escaped characters literally, unless JSON requires the escape.
+    Minimal,
+    /// Escape every character outside of ASCII.
+    Ascii,
+}
+
+impl FromStr for EscapeMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "preserve" => Ok(EscapeMode::Preserve),
+            "minimal" => Ok(EscapeMode::Minimal),
+            "ascii" => Ok(EscapeMode::Ascii),
+            _ => Err(format!(
+                "Invalid escape mode {:?}, expected preserve, minimal or ascii",
+                value
+            )),
+        }
+    }
+}
+
+/// The case of the hex digits in `\u` escapes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HexCase {
+    /// Keep the case of existing escapes, and write new ones in lowercase.
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
+}
+
+impl FromStr for HexCase {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "preserve" => Ok(HexCase::Preserve),
+            "lower" => Ok(HexCase::Lower),
+            "upper" => Ok(HexCase::Upper),
+            _ => Err(format!(
+                "Invalid hex case {:?}, expected preserve, lower or upper",
+                value
+            )),
+        }
+    }
+}
+
+/// How the contents of string literals are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StringEscapes {
+    pub mode: EscapeMode,
+    pub hex_case: HexCase,
+    /// Whether to write `\/` as `/`.
+    pub unescape_slash: bool,
+}
+
+impl StringEscapes {
+    /// Whether every string is written as it is in the source.
+    pub fn is_preserve(&self) -> bool {
+        *self == StringEscapes::default()
+    }
+
+    /// Rewrites the keys and string values in the file.
+    pub fn apply(&self, file: &mut JSONFile) {
+        self.apply_node(&mut file.value);
+    }
+
+    fn apply_node(&self, node: &mut JSONNode) {
+        match node {
+            JSONNode::String(string) => self.apply_string(string),
+            JSONNode::Array(array) => {
+                for item in &mut array.items {
+                    self.apply_node(item);
+                }
+            }
+            JSONNode::Object(object) => {
+                for member in &mut object.members {
+                    self.apply_string(&mut member.key);
+                    self.apply_node(&mut member.value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_string(&self, string: &mut JSONString) {
+        string.raw = self.rewrite(&string.raw);
+    }
+
+    /// Returns the string literal, with its escapes rewritten.
+    pub fn rewrite(&self, literal: &str) -> String {
+        let inner = literal
+            .strip_prefix('"')
+            .and_then(|inner| inner.strip_suffix('"'))
+            .unwrap_or(literal);
+        let mut rewritten = String::with_capacity(literal.len());
+        rewritten.push('"');
+        let mut chars = inner.chars();
+        while let Some(char) = chars.next() {
+            if char != '\\' {
+                match self.mode {
+                    EscapeMode::Ascii if !char.is_ascii() => {
+                        self.push_escaped(&mut rewritten, char)
+                    }
+                    _ => rewritten.push(char),
+                }
+                continue;
+            }
+            match chars.next() {
+                Some('u') => self.rewrite_unicode_escape(&mut chars, &mut rewritten),
+                Some('/') if self.unescape_slash => rewritten.push('/'),
+                Some(escaped) => {
+                    rewritten.push('\\');
+                    rewritten.push(escaped);
+                }
+                None => rewritten.push('\\'),
+            }
+        }
+        rewritten.push('"');
+        rewritten
+    }
+
+    /// Rewrites a `\u` escape, and the escaped low surrogate following it.
+    fn rewrite_unicode_escape(&self, chars: &mut std::str::Chars, rewritten: &mut String) {
+        let start = chars.as_str();
+        let Some(unit) = hex_escape(chars) else {
+            rewritten.push_str("\\u");
+            return;
+        };
+        let mut units = vec![unit];
+        if (0xD800..0xDC00).contains(&unit) && chars.as_str().starts_with("\\u") {
+            let mut lookahead = chars.clone();
+            lookahead.nth(1);
+            if let Some(low @ 0xDC00..=0xDFFF) = hex_escape(&mut lookahead) {
+                units.push(low);
+                *chars = lookahead;
+            }
+        }
+        let escape = &start[..start.len() - chars.as_str().len()];
+
+        // lone surrogates can only be written as escapes.
+        let decoded = char::decode_utf16(units).next().and_then(Result::ok);
+        let needs_escape = |char: char| {
+            char < ' '
+                || char == '"'
+                || char == '\\'
+                || self.mode == EscapeMode::Ascii && !char.is_ascii()
+        };
+        match decoded {
+            Some(char) if self.mode != EscapeMode::Preserve && !needs_escape(char) => {
+                rewritten.push(char)
+            }
+            _ => {
+                rewritten.push_str("\\u");
+                rewritten.push_str(&match self.hex_case {
+                    HexCase::Preserve => escape.to_string(),
+                    HexCase::Lower => escape.to_lowercase(),
+                    // keep the `u` of a second escape lowercase.
+                    HexCase::Upper => escape.to_uppercase().replace("\\U", "\\u"),
+                });
+            }
+        }
+    }
+
+    /// Writes the character as `\u` escapes, as a surrogate pair if needed.
+    fn push_escaped(&self, rewritten: &mut String, char: char) {
+        for unit in char.encode_utf16(&mut [0; 2]) {
+            match self.hex_case {
+                HexCase::Upper => rewritten.push_str(&format!("\\u{:04X}", unit)),
+                _ => rewritten.push_str(&format!("\\u{:04x}", unit)),
+            }
+        }
+    }
+}
@@ -1,5 +1,10 @@
 //! Contains the token formatter struct, which consumes and formats tokens.
-use crate::tokenizer;
+use std::iter::Peekable;
+
+use crate::{
+    options::{BlankLines, FormatOptions},
+    tokenizer,
+};
 
 /// Formatter uses 2 space indents.
 pub const INDENT: &str = "  ";
@@ -7,9 +12,11 @@ pub const INDENT: &str = "  ";
 /// Token formatter struct. The iterator yields formatted tokens.
 ///
 /// Check [TokenFormatter::next] for formatting rules.
-pub struct TokenFormatter<T> {
-    pub tokens: T,
+pub struct TokenFormatter<T: Iterator> {
+    pub tokens: Peekable<T>,
     indent_level: usize,
+    newline: &'static str,
+    blank_lines: BlankLines,
 }
 
 impl<'a, T> TokenFormatter<T>
@@ -18,10 +25,27 @@ where
 {
     pub fn new(tokens: T) -> Self {
         Self {
-            tokens,
+            tokens: tokens.peekable(),
             indent_level: 0,
+            newline: "\n",
+            blank_lines: BlankLines::Remove,
+        }
+    }
+
+    /// Creates a formatter for tokens of the given source, using the options.
+    pub fn with_options(tokens: T, source: &str, options: &FormatOptions) -> Self {
+        Self {
+            newline: options.line_ending.newline(source),
+            blank_lines: options.blank_lines,
+            ..Self::new(tokens)
         }
     }
+
+    /// Starts at the given indent level, to format values nested that deep.
+    pub fn with_indent_level(mut self, indent_level: usize) -> Self {
+        self.indent_level = indent_level;
+        self
+    }
 }
 
 impl<'a, T> Iterator for TokenFormatter<T>
@@ -35,12 +59,29 @@ where
     ///   and write a newline and the current indent.
     /// - Write the token itself, trimmed of whitespace.
     /// - After every colon, write a space.
-    /// - After every comma, write a newline.
-    /// - After every closing bracket and brace, increase indent level by 1,
+    /// - After every comma, write a newline, and a blank line if the source
+    ///   had one before the next token and blank lines are preserved.
+    /// - After every opening bracket and brace, increase indent level by 1,
     ///   and write a newline and current indent.
+    /// - Write empty arrays and objects as `[]` and `{}`.
     fn next(&mut self) -> Option<Self::Item> {
         let token = self.tokens.next()?;
 
+        if let Some(next) = self.tokens.next_if(|next| {
+            matches!(
+                (&token.token_type, &next.token_type),
+                (
+                    tokenizer::TokenType::LeftBracket,
+                    tokenizer::TokenType::RightBracket
+                ) | (
+                    tokenizer::TokenType::LeftBrace,
+                    tokenizer::TokenType::RightBrace
+                )
+            )
+        }) {
+            return Some(format!("{}{}", token, next));
+        }
+
         let mut formatted_token = String::new();
 
         macro_rules! print_indent {
@@ -54,7 +95,7 @@ where
         if let tokenizer::TokenType::RightBracket | tokenizer::TokenType::RightBrace =
             token.token_type
         {
-            formatted_token.push('\n');
+            formatted_token.push_str(self.newline);
             self.indent_level = self.indent_level.saturating_sub(1);
             print_indent!();
         }
@@ -63,7 +104,15 @@ where
 
         match token.token_type {
             tokenizer::TokenType::Comma => {
-                formatted_token.push('\n');
+                formatted_token.push_str(self.newline);
+                if self.blank_lines == BlankLines::Preserve
+                    && self
+                        .tokens
+                        .peek()
+                        .is_some_and(|next| next.blank_line_before)
+                {
+                    formatted_token.push_str(self.newline);
+                }
                 print_indent!();
             }
             tokenizer::TokenType::Colon => {
@@ -75,11 +124,11 @@ where
         if let tokenizer::TokenType::LeftBracket | tokenizer::TokenType::LeftBrace =
             token.token_type
         {
-            formatted_token.push('\n');
+            formatted_token.push_str(self.newline);
             self.indent_level += 1;
             print_indent!();
         }
 
-        return Some(formatted_token);
+        Some(formatted_token)
     }
 }
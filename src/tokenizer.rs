@@ -1,10 +1,13 @@
 //! Tokenizes a given JSON string, without validating its syntax.
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
-use crate::errors;
+use crate::{errors, limits::Limits};
+
+/// The UTF-8 byte order mark, which is allowed at the very start of the source.
+pub const BOM: char = '\u{FEFF}';
 
 /// The kinds of tokens produced by the tokenizer.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TokenType<'a> {
     String(&'a str),
     Number(&'a str),
@@ -19,10 +22,38 @@ pub enum TokenType<'a> {
 }
 
 /// Tokens produced by the tokenizer.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Token<'a> {
     pub token_type: TokenType<'a>,
+    /// Offset of the first byte of the token in the source.
     pub byte_offset: usize,
+    /// Offset of the byte just past the end of the token in the source.
+    pub end_offset: usize,
+    /// Whether a blank line separates the token from the one before it.
+    pub blank_line_before: bool,
+}
+
+impl<'a> Token<'a> {
+    /// Creates a token spanning its own source text, starting at `byte_offset`.
+    pub fn new(token_type: TokenType<'a>, byte_offset: usize) -> Self {
+        let len = match token_type {
+            TokenType::String(text) | TokenType::Number(text) => text.len(),
+            TokenType::Boolean(true) | TokenType::Null => 4,
+            TokenType::Boolean(false) => 5,
+            _ => 1,
+        };
+        Self {
+            token_type,
+            byte_offset,
+            end_offset: byte_offset + len,
+            blank_line_before: false,
+        }
+    }
+
+    /// Returns the byte range of the token in the source.
+    pub fn span(&self) -> Range<usize> {
+        self.byte_offset..self.end_offset
+    }
 }
 
 impl<'a> Display for Token<'a> {
@@ -46,11 +77,31 @@ impl<'a> Display for Token<'a> {
 struct Tokenizer<'a> {
     pub source: &'a str,
     pub filepath: String,
+    max_string_length: usize,
 }
 
 impl<'a> Tokenizer<'a> {
-    fn new(source: &'a str, filepath: String) -> Self {
-        Self { source, filepath }
+    fn new(source: &'a str, filepath: String, limits: &Limits) -> Self {
+        Self {
+            source,
+            filepath,
+            max_string_length: limits.max_string_length,
+        }
+    }
+
+    fn error(
+        &self,
+        span: Range<usize>,
+        message: &str,
+        code: errors::ErrorCode,
+    ) -> errors::InvalidSyntaxDiagnostic {
+        errors::InvalidSyntaxDiagnostic::new(
+            &self.filepath,
+            self.source,
+            span.into(),
+            message.to_string(),
+        )
+        .with_code(code)
     }
 
     fn tokenize(&self) -> Result<Vec<Token<'a>>, errors::FormatJsonError> {
@@ -60,36 +111,37 @@ impl<'a> Tokenizer<'a> {
         while let Some((byte_offset, char)) = chars.next() {
             // special cases first: strings and numbers
             if char == '"' {
-                let string_token = self.extract_string(byte_offset).ok_or_else(|| {
-                    errors::InvalidSyntaxDiagnostic::new(
-                        &self.filepath,
-                        self.source,
-                        byte_offset.into(),
-                        "Expected end of string".to_string(),
-                    )
-                })?;
-                tokens.push(Token {
-                    token_type: TokenType::String(string_token),
-                    byte_offset,
-                });
+                let string_token = self.extract_string(byte_offset)?;
+                if string_token.len() - 2 > self.max_string_length {
+                    return Err(self
+                        .error(
+                            byte_offset..byte_offset + string_token.len(),
+                            &format!(
+                                "String is longer than the limit of {} bytes",
+                                self.max_string_length
+                            ),
+                            errors::ErrorCode::LimitExceeded,
+                        )
+                        .into());
+                }
+                tokens.push(Token::new(TokenType::String(string_token), byte_offset));
                 // consume the extra tokens, till the end of the string.
                 // we don't need a length check, because a string will always be
                 // at least 2 in len().
                 chars.nth(string_token.chars().count() - 2);
                 continue;
             } else if let '0'..='9' | '-' = char {
-                let number_token = self.extract_number(byte_offset).ok_or_else(|| {
-                    errors::InvalidSyntaxDiagnostic::new(
-                        &self.filepath,
-                        self.source,
-                        byte_offset.into(),
-                        "Expected number token".to_string(),
-                    )
-                })?;
-                tokens.push(Token {
-                    token_type: TokenType::Number(number_token),
-                    byte_offset,
-                });
+                let number_token = self.extract_number(byte_offset);
+                if !is_valid_number(number_token) {
+                    return Err(self
+                        .error(
+                            byte_offset..byte_offset + number_token.len(),
+                            "Invalid number",
+                            errors::ErrorCode::InvalidNumber,
+                        )
+                        .into());
+                }
+                tokens.push(Token::new(TokenType::Number(number_token), byte_offset));
                 if number_token.len() >= 2 {
                     // consume the extra tokens, till the end of the number
                     chars.nth(number_token.len() - 2);
@@ -97,118 +149,195 @@ impl<'a> Tokenizer<'a> {
                 continue;
             } else if "tfn".contains(char) {
                 let special_token = self.extract_boolean_or_null(byte_offset).ok_or_else(|| {
-                    errors::InvalidSyntaxDiagnostic::new(
-                        &self.filepath,
-                        self.source,
-                        byte_offset.into(),
-                        "Expected true, false, or null".to_string(),
+                    self.error(
+                        byte_offset..byte_offset + 1,
+                        "Expected true, false, or null",
+                        errors::ErrorCode::InvalidLiteral,
                     )
                 })?;
-                tokens.push(Token {
-                    token_type: TokenType::Number(special_token),
-                    byte_offset,
-                });
-                // consume the extra tokens, till the end of the number
-                chars.nth(special_token.chars().count() - 2);
+                let token_type = match special_token {
+                    "true" => TokenType::Boolean(true),
+                    "false" => TokenType::Boolean(false),
+                    _ => TokenType::Null,
+                };
+                tokens.push(Token::new(token_type, byte_offset));
+                // consume the extra tokens, till the end of the literal
+                chars.nth(special_token.len() - 2);
                 continue;
             }
 
-            if " \n\t".contains(char) {
-                // ignore all whitespace
+            if " \n\t\r".contains(char) || (char == BOM && byte_offset == 0) {
+                // ignore all whitespace, and a leading byte order mark
             } else if char == ',' {
-                tokens.push(Token {
-                    token_type: TokenType::Comma,
-                    byte_offset,
-                })
+                tokens.push(Token::new(TokenType::Comma, byte_offset))
             } else if char == ':' {
-                tokens.push(Token {
-                    token_type: TokenType::Colon,
-                    byte_offset,
-                })
+                tokens.push(Token::new(TokenType::Colon, byte_offset))
             } else if char == '[' {
-                tokens.push(Token {
-                    token_type: TokenType::LeftBracket,
-                    byte_offset,
-                })
+                tokens.push(Token::new(TokenType::LeftBracket, byte_offset))
             } else if char == ']' {
-                tokens.push(Token {
-                    token_type: TokenType::RightBracket,
-                    byte_offset,
-                })
+                tokens.push(Token::new(TokenType::RightBracket, byte_offset))
             } else if char == '{' {
-                tokens.push(Token {
-                    token_type: TokenType::LeftBrace,
-                    byte_offset,
-                })
+                tokens.push(Token::new(TokenType::LeftBrace, byte_offset))
             } else if char == '}' {
-                tokens.push(Token {
-                    token_type: TokenType::RightBrace,
-                    byte_offset,
-                })
+                tokens.push(Token::new(TokenType::RightBrace, byte_offset))
             } else {
-                return Err(errors::FormatJsonError::InvalidSyntax(
-                    errors::InvalidSyntaxDiagnostic::new(
-                        &self.filepath,
-                        self.source,
-                        byte_offset.into(),
-                        "Unexpected token".to_string(),
-                    ),
-                ));
+                return Err(self
+                    .error(
+                        byte_offset..byte_offset + char.len_utf8(),
+                        "Unexpected token",
+                        errors::ErrorCode::UnexpectedCharacter,
+                    )
+                    .into());
             }
         }
+
+        // record where the author left blank lines between tokens.
+        let mut previous_end = 0;
+        // a blank line before a comma moves after it, where the formatter breaks lines.
+        let mut after_comma = false;
+        for token in &mut tokens {
+            let whitespace = &self.source[previous_end..token.byte_offset];
+            let blank_line = previous_end > 0 && count_line_breaks(whitespace) >= 2;
+            if token.token_type == TokenType::Comma {
+                after_comma = blank_line;
+            } else {
+                token.blank_line_before = blank_line || after_comma;
+                after_comma = false;
+            }
+            previous_end = token.end_offset;
+        }
         Ok(tokens)
     }
 
-    fn extract_string(&self, index: usize) -> Option<&'a str> {
-        let slice = self.source.get(index + 1..)?;
+    fn extract_string(&self, index: usize) -> Result<&'a str, errors::FormatJsonError> {
+        let slice = &self.source[index + 1..];
         let mut chars = slice.char_indices();
         while let Some((i, char)) = chars.next() {
             if char == '\\' {
-                // skip the next character
-                chars.next();
+                let escape_index = index + 1 + i;
+                let escape_len = match chars.next() {
+                    Some((_, '"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't')) => continue,
+                    Some((_, 'u')) => {
+                        let hex_digits = slice[i + 2..]
+                            .chars()
+                            .take(4)
+                            .take_while(char::is_ascii_hexdigit)
+                            .count();
+                        if hex_digits == 4 {
+                            chars.nth(3);
+                            continue;
+                        }
+                        2 + hex_digits
+                    }
+                    Some((_, escaped)) => 1 + escaped.len_utf8(),
+                    // unterminated, which is reported below.
+                    None => break,
+                };
+                return Err(self
+                    .error(
+                        escape_index..escape_index + escape_len,
+                        "Invalid escape sequence in string",
+                        errors::ErrorCode::InvalidEscape,
+                    )
+                    .into());
             } else if char == '"' {
                 // found the closing quote. Return string.
                 let end_quote_index = index + 1 + i;
-                return Some(&self.source[index..=end_quote_index]);
+                return Ok(&self.source[index..=end_quote_index]);
             } else if char <= '\u{001f}' {
                 // Control characters (U+0000–U+001F), e.g. a literal newline,
                 // must be escaped inside a JSON string. An unescaped one means
                 // the string is invalid, so bail out.
-                return None;
+                let char_index = index + 1 + i;
+                return Err(self
+                    .error(
+                        char_index..char_index + 1,
+                        "Unescaped control character in string",
+                        errors::ErrorCode::ControlCharacterInString,
+                    )
+                    .with_related((index..index + 1).into(), "String starts here")
+                    .into());
             }
         }
 
-        return None;
+        Err(self
+            .error(
+                index..index + 1,
+                "Expected end of string",
+                errors::ErrorCode::UnterminatedString,
+            )
+            .into())
     }
-    fn extract_number(&self, index: usize) -> Option<&'a str> {
-        let slice = self.source.get(index + 1..)?;
-        slice
+    fn extract_number(&self, index: usize) -> &'a str {
+        let slice = &self.source[index + 1..];
+        let end_index = slice
             .find(|char| {
-                if let '0'..='9' | '-' | '.' | 'e' | 'E' = char {
+                if let '0'..='9' | '-' | '+' | '.' | 'e' | 'E' = char {
                     return false;
                 }
                 // unknown character: found the end of the number
-                return true;
+                true
             })
             .map(|i| index + 1 + i)
-            // end_index is the character that's not part of the number.
-            // so the slice will not include it.
-            .map(|end_index| &self.source[index..end_index])
+            // a number at the very end of the source.
+            .unwrap_or(self.source.len());
+        // end_index is the character that's not part of the number.
+        // so the slice will not include it.
+        &self.source[index..end_index]
     }
     fn extract_boolean_or_null(&self, index: usize) -> Option<&'a str> {
-        let slice = self.source.get(index..)?;
-        if slice.get(..4)? == "true" {
-            return Some("true");
+        let slice = &self.source[index..];
+        ["true", "false", "null"]
+            .into_iter()
+            .find(|literal| slice.starts_with(literal))
+    }
+}
+
+/// Counts the line breaks in the text, where `\r\n`, `\r` and `\n` each
+/// count as one.
+fn count_line_breaks(text: &str) -> usize {
+    text.matches('\n').count() + text.matches('\r').count() - text.matches("\r\n").count()
+}
+
+/// Checks the number against the JSON grammar:
+/// `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`
+fn is_valid_number(number: &str) -> bool {
+    let bytes = number.as_bytes();
+    let mut index = 0;
+    let digits = |index: &mut usize| {
+        let start = *index;
+        while bytes.get(*index).is_some_and(u8::is_ascii_digit) {
+            *index += 1;
+        }
+        *index - start
+    };
+
+    if bytes.get(index) == Some(&b'-') {
+        index += 1;
+    }
+    match bytes.get(index) {
+        Some(b'0') => index += 1,
+        Some(b'1'..=b'9') => {
+            digits(&mut index);
         }
-        if slice.get(..5)? == "false" {
-            return Some("false");
+        _ => return false,
+    }
+    if bytes.get(index) == Some(&b'.') {
+        index += 1;
+        if digits(&mut index) == 0 {
+            return false;
         }
-        if slice.get(..4)? == "null" {
-            return Some("null");
+    }
+    if let Some(b'e' | b'E') = bytes.get(index) {
+        index += 1;
+        if let Some(b'+' | b'-') = bytes.get(index) {
+            index += 1;
+        }
+        if digits(&mut index) == 0 {
+            return false;
         }
-
-        return None;
     }
+    index == bytes.len()
 }
 
 /// Returns tokens corresponding to the source.
@@ -216,5 +345,14 @@ pub fn tokenize<'a>(
     source: &'a str,
     filepath: String,
 ) -> Result<Vec<Token<'a>>, errors::FormatJsonError> {
-    Tokenizer::new(source, filepath).tokenize()
+    tokenize_with_limits(source, filepath, &Limits::default())
+}
+
+/// Returns tokens corresponding to the source, enforcing the string length limit.
+pub fn tokenize_with_limits<'a>(
+    source: &'a str,
+    filepath: String,
+    limits: &Limits,
+) -> Result<Vec<Token<'a>>, errors::FormatJsonError> {
+    Tokenizer::new(source, filepath, limits).tokenize()
 }
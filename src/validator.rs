@@ -1,9 +1,13 @@
 //! Validates the tokens generated by the [tokenizer][crate::tokenizer].
-use std::{iter::Peekable, slice::Iter};
+use std::{iter::Peekable, ops::Range, slice::Iter};
 
 use thiserror::Error;
 
-use crate::tokenizer::{Token, TokenType};
+use crate::{
+    errors::{ErrorCode, InvalidSyntaxDiagnostic},
+    limits::Limits,
+    tokenizer::{Token, TokenType},
+};
 
 /// Error class raised by the validator.
 #[derive(Error, Debug)]
@@ -14,6 +18,18 @@ pub enum ValidationError {
     UnexpectedToken(usize, String),
     #[error("Unexpected end of file")]
     UnexpectedEOF(usize),
+    #[error("Trailing comma")]
+    TrailingComma(usize),
+    #[error("Missing comma before {1:?}")]
+    MissingComma(usize, String),
+    #[error("Expected a colon, found {1:?}")]
+    MissingColon(usize, String),
+    #[error("Expected a string key, found {1:?}")]
+    InvalidKey(usize, String),
+    #[error("Nesting is deeper than the limit of {1}")]
+    DepthLimitExceeded(usize, usize),
+    #[error("Contains more than the limit of {1} members")]
+    TooManyMembers(usize, usize),
 }
 
 impl ValidationError {
@@ -21,15 +37,139 @@ impl ValidationError {
         match self {
             ValidationError::EmptyFile(x)
             | ValidationError::UnexpectedToken(x, _)
-            | ValidationError::UnexpectedEOF(x) => *x,
+            | ValidationError::UnexpectedEOF(x)
+            | ValidationError::TrailingComma(x)
+            | ValidationError::MissingComma(x, _)
+            | ValidationError::MissingColon(x, _)
+            | ValidationError::InvalidKey(x, _)
+            | ValidationError::DepthLimitExceeded(x, _)
+            | ValidationError::TooManyMembers(x, _) => *x,
         }
     }
+
+    /// Returns the byte range in the source that the error points at.
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            ValidationError::UnexpectedToken(x, token)
+            | ValidationError::MissingComma(x, token)
+            | ValidationError::MissingColon(x, token)
+            | ValidationError::InvalidKey(x, token) => *x..*x + token.len(),
+            ValidationError::TrailingComma(x)
+            | ValidationError::DepthLimitExceeded(x, _)
+            | ValidationError::TooManyMembers(x, _) => *x..*x + 1,
+            _ => self.byte_offset()..self.byte_offset(),
+        }
+    }
+
+    /// Returns the code identifying the kind of error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ValidationError::EmptyFile(_) => ErrorCode::EmptyFile,
+            ValidationError::UnexpectedToken(_, _) => ErrorCode::UnexpectedToken,
+            ValidationError::UnexpectedEOF(_) => ErrorCode::UnexpectedEof,
+            ValidationError::TrailingComma(_) => ErrorCode::TrailingComma,
+            ValidationError::MissingComma(_, _) => ErrorCode::MissingComma,
+            ValidationError::MissingColon(_, _) => ErrorCode::MissingColon,
+            ValidationError::InvalidKey(_, _) => ErrorCode::InvalidKey,
+            ValidationError::DepthLimitExceeded(_, _) | ValidationError::TooManyMembers(_, _) => {
+                ErrorCode::LimitExceeded
+            }
+        }
+    }
+
+    /// Converts the error into a diagnostic for the given source and tokens.
+    ///
+    /// An unexpected end of file also points at the bracket left unclosed.
+    pub fn to_diagnostic(
+        &self,
+        filepath: &str,
+        source: &str,
+        tokens: &[Token],
+    ) -> InvalidSyntaxDiagnostic {
+        let diagnostic =
+            InvalidSyntaxDiagnostic::new(filepath, source, self.span().into(), self.to_string());
+        self.decorate(diagnostic, tokens)
+    }
+
+    /// Adds the error code and related labels to the diagnostic.
+    fn decorate(
+        &self,
+        diagnostic: InvalidSyntaxDiagnostic,
+        tokens: &[Token],
+    ) -> InvalidSyntaxDiagnostic {
+        let diagnostic = diagnostic.with_code(self.code());
+        match self {
+            ValidationError::UnexpectedEOF(_) => match unclosed_bracket(tokens) {
+                Some(token) => diagnostic.with_related(token.span().into(), "Unclosed bracket"),
+                None => diagnostic,
+            },
+            _ => diagnostic,
+        }
+    }
+}
+
+/// Converts the errors into a single diagnostic, which renders all of them.
+///
+/// Returns `None` if there are no errors.
+pub fn errors_to_diagnostic(
+    errors: &[ValidationError],
+    filepath: &str,
+    source: &str,
+    tokens: &[Token],
+) -> Option<InvalidSyntaxDiagnostic> {
+    let (first, rest) = errors.split_first()?;
+    let diagnostic = first.to_diagnostic(filepath, source, tokens);
+    let others = rest
+        .iter()
+        .map(|error| {
+            let sibling = diagnostic.sibling(error.span().into(), error.to_string());
+            error.decorate(sibling, tokens)
+        })
+        .collect();
+    Some(diagnostic.with_others(others))
+}
+
+/// Returns the innermost opening bracket or brace that is never closed.
+pub fn unclosed_bracket<'a, 'b>(tokens: &'b [Token<'a>]) -> Option<&'b Token<'a>> {
+    unclosed_brackets(tokens).pop()
+}
+
+/// Returns every opening bracket and brace that is never closed, outermost first.
+pub fn unclosed_brackets<'a, 'b>(tokens: &'b [Token<'a>]) -> Vec<&'b Token<'a>> {
+    let mut open_brackets: Vec<&Token> = vec![];
+    for token in tokens {
+        match token.token_type {
+            TokenType::LeftBracket | TokenType::LeftBrace => open_brackets.push(token),
+            // Ignore stray closing brackets that don't match the open one.
+            TokenType::RightBracket | TokenType::RightBrace => {
+                let matching = match token.token_type {
+                    TokenType::RightBracket => TokenType::LeftBracket,
+                    _ => TokenType::LeftBrace,
+                };
+                if open_brackets.last().map(|open| &open.token_type) == Some(&matching) {
+                    open_brackets.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+    open_brackets
+}
+
+/// Returns true if the token can start a JSON value.
+fn starts_value(token: &Token) -> bool {
+    !matches!(
+        token.token_type,
+        TokenType::RightBracket | TokenType::RightBrace | TokenType::Comma | TokenType::Colon
+    )
 }
 
 /// Yields tokens, while maintaining the byte offset of the latest token.
 pub struct TokenIterator<'a> {
     pub tokens: Peekable<Iter<'a, Token<'a>>>,
     pub last_byte_offset: usize,
+    /// The byte offset just past the latest token, where an EOF is reported.
+    pub last_end_offset: usize,
 }
 
 impl<'a> Iterator for TokenIterator<'a> {
@@ -39,6 +179,7 @@ impl<'a> Iterator for TokenIterator<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         let token = self.tokens.next()?;
         self.last_byte_offset = token.byte_offset;
+        self.last_end_offset = token.end_offset;
         Some(token)
     }
 }
@@ -48,19 +189,15 @@ impl<'a> TokenIterator<'a> {
         Self {
             tokens: tokens.peekable(),
             last_byte_offset: 0,
+            last_end_offset: 0,
         }
     }
 
-    fn next_or_eof(&mut self) -> Result<&Token, ValidationError> {
-        self.next()
-            .ok_or(ValidationError::UnexpectedEOF(self.last_byte_offset))
-    }
-
-    pub fn peek(&mut self) -> Result<&Token, ValidationError> {
+    pub fn peek(&mut self) -> Result<&'a Token<'a>, ValidationError> {
         self.tokens
             .peek()
             .copied()
-            .ok_or(ValidationError::UnexpectedEOF(self.last_byte_offset))
+            .ok_or(ValidationError::UnexpectedEOF(self.last_end_offset))
     }
 }
 
@@ -68,109 +205,347 @@ impl<'a> TokenIterator<'a> {
 ///
 /// Raises a [ValidationError] if validation fails.
 pub fn validate(tokens: &[Token]) -> Result<(), ValidationError> {
-    let mut tokens = TokenIterator::new(tokens.iter());
-    validate_recursive(&mut tokens)?;
-
-    // now tokens should be empty. otherwise raise an error.
-    if let Some(token) = tokens.next() {
-        return Err(ValidationError::UnexpectedToken(
-            token.byte_offset,
-            token.to_string(),
-        ));
+    match validate_all(tokens, 1).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(()),
     }
-    Ok(())
 }
 
-fn validate_recursive<'a>(tokens: &mut TokenIterator) -> Result<(), ValidationError> {
-    if let Some(token) = tokens.next() {
-        match token.token_type {
-            TokenType::RightBracket
-            | TokenType::RightBrace
-            | TokenType::Comma
-            | TokenType::Colon => Err(ValidationError::UnexpectedToken(
-                token.byte_offset,
-                token.to_string(),
-            )),
+/// Validates the tokens, recovering from errors to find as many as possible.
+///
+/// Returns up to `max_errors` errors, in the order they appear in the source.
+pub fn validate_all(tokens: &[Token], max_errors: usize) -> Vec<ValidationError> {
+    validate_with_limits(tokens, max_errors, &Limits::default())
+}
 
-            TokenType::String(_)
-            | TokenType::Number(_)
-            | TokenType::Boolean(_)
-            | TokenType::Null => Ok(()),
+/// Validates the tokens like [validate_all], enforcing the depth and member
+/// limits. Exceeding a limit stops validation.
+pub fn validate_with_limits(
+    tokens: &[Token],
+    max_errors: usize,
+    limits: &Limits,
+) -> Vec<ValidationError> {
+    let mut validator = Validator {
+        tokens: TokenIterator::new(tokens.iter()),
+        errors: vec![],
+        max_errors: max_errors.max(1),
+        limits: *limits,
+        containers: vec![],
+    };
+    // Stopping early only means we have found enough errors.
+    let _ = validator.validate_document();
+    validator.errors
+}
+
+/// Signals that validation should stop: at the end of the file, when enough
+/// errors have been found, or when a limit is exceeded.
+struct Stop;
+
+/// What an open array or object expects next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    /// An array item.
+    Item,
+    /// An object member, starting with its key.
+    Member,
+    /// The colon between a key and its value, then the value.
+    Colon,
+    /// A comma or the closing token, after an item or member.
+    Separator,
+}
+
+/// An array or object being validated.
+struct Container {
+    /// The token closing the container.
+    closing: TokenType<'static>,
+    expect: Expect,
+    /// The number of items or members seen so far.
+    members: usize,
+}
+
+/// A validator which records errors instead of returning on the first one.
+///
+/// It keeps a stack of open arrays and objects instead of recursing, so deeply
+/// nested input can't overflow the call stack.
+///
+/// After an error it resyncs at the next comma, closing bracket or object key,
+/// so that one mistake doesn't cascade into many errors.
+struct Validator<'a> {
+    tokens: TokenIterator<'a>,
+    errors: Vec<ValidationError>,
+    max_errors: usize,
+    limits: Limits,
+    /// The arrays and objects we are currently inside, innermost last.
+    containers: Vec<Container>,
+}
 
-            TokenType::LeftBracket => validate_array(tokens),
-            TokenType::LeftBrace => validate_object(tokens),
+impl<'a> Validator<'a> {
+    /// Records an error, stopping once `max_errors` have been found.
+    fn error(&mut self, error: ValidationError) -> Result<(), Stop> {
+        self.errors.push(error);
+        if self.errors.len() >= self.max_errors {
+            return Err(Stop);
+        }
+        Ok(())
+    }
+
+    /// Peeks the next token, stopping at the end of the file.
+    fn peek(&mut self) -> Result<&'a Token<'a>, Stop> {
+        match self.tokens.peek() {
+            Ok(token) => Ok(token),
+            Err(error) => {
+                self.errors.push(error);
+                Err(Stop)
+            }
         }
-    } else {
-        Err(ValidationError::EmptyFile(tokens.last_byte_offset))
     }
-}
 
-fn validate_array<'a>(tokens: &mut TokenIterator) -> Result<(), ValidationError> {
-    // Special case: in an empty array we see the closing bracket immediately.
-    // in that case, consume the bracket and early exit.
-    if matches!(tokens.peek()?.token_type, TokenType::RightBracket) {
-        tokens
-            .next()
-            .expect("We peeked, this should not have failed.");
-        return Ok(());
+    /// Returns true if the closing token closes an array or object we're in.
+    fn closes_open_container(&self, token: &Token) -> bool {
+        self.containers
+            .iter()
+            .any(|container| container.closing == token.token_type)
     }
 
-    loop {
-        // Expect a value, followed by a comma or a final closing bracket
-        validate_recursive(tokens)?;
-        let token = tokens.next_or_eof()?;
-        match token.token_type {
-            TokenType::Comma => (),
-            TokenType::RightBracket => return Ok(()),
-            _ => {
-                return Err(ValidationError::UnexpectedToken(
-                    token.byte_offset,
-                    token.to_string(),
-                ))
+    /// Sets what the innermost container expects next.
+    fn expect(&mut self, expect: Expect) {
+        if let Some(container) = self.containers.last_mut() {
+            container.expect = expect;
+        }
+    }
+
+    fn validate_document(&mut self) -> Result<(), Stop> {
+        if self.tokens.tokens.peek().is_none() {
+            return self.error(ValidationError::EmptyFile(self.tokens.last_byte_offset));
+        }
+        self.validate_value()?;
+        while let Some(container) = self.containers.last() {
+            match container.expect {
+                Expect::Item => {
+                    self.count_member()?;
+                    self.expect(Expect::Separator);
+                    self.validate_value()?;
+                }
+                Expect::Member => {
+                    self.count_member()?;
+                    self.validate_key()?;
+                }
+                Expect::Colon => self.validate_colon()?,
+                Expect::Separator => {
+                    let is_object = container.closing == TokenType::RightBrace;
+                    if self.validate_separator(is_object)? {
+                        self.containers.pop();
+                    } else {
+                        self.expect(match is_object {
+                            true => Expect::Member,
+                            false => Expect::Item,
+                        });
+                    }
+                }
             }
         }
+
+        // now tokens should be empty. otherwise raise an error.
+        if let Some(token) = self.tokens.next() {
+            self.error(unexpected(token))?;
+        }
+        Ok(())
     }
-}
 
-fn validate_object<'a>(tokens: &mut TokenIterator) -> Result<(), ValidationError> {
-    // Special case: in an empty array we see the closing bracket immediately.
-    // in that case, consume the bracket and early exit.
-    if matches!(tokens.peek()?.token_type, TokenType::RightBrace) {
-        tokens
-            .next()
-            .expect("We peeked, this should not have failed.");
-        return Ok(());
+    /// Counts an item or member of the innermost container, stopping when
+    /// there are too many.
+    fn count_member(&mut self) -> Result<(), Stop> {
+        let max_members = self.limits.max_members;
+        let container = self.containers.last_mut().expect("Inside a container");
+        container.members += 1;
+        if container.members > max_members {
+            let offset = self.peek()?.byte_offset;
+            self.errors
+                .push(ValidationError::TooManyMembers(offset, max_members));
+            return Err(Stop);
+        }
+        Ok(())
     }
 
-    loop {
-        // Expect a string key, followed by a colon
-        let token = tokens.next_or_eof()?;
-        if !matches!(token.token_type, TokenType::String(_)) {
-            return Err(ValidationError::UnexpectedToken(
-                token.byte_offset,
-                token.to_string(),
+    /// Validates a value. Arrays and objects are only opened here, and
+    /// validated by the loop in [Validator::validate_document].
+    fn validate_value(&mut self) -> Result<(), Stop> {
+        let token = self.peek()?;
+        match token.token_type {
+            TokenType::LeftBracket => {
+                self.tokens.next();
+                self.open_container(token, TokenType::RightBracket)
+            }
+            TokenType::LeftBrace => {
+                self.tokens.next();
+                self.open_container(token, TokenType::RightBrace)
+            }
+            TokenType::String(_)
+            | TokenType::Number(_)
+            | TokenType::Boolean(_)
+            | TokenType::Null => {
+                self.tokens.next();
+                Ok(())
+            }
+            TokenType::Comma | TokenType::RightBracket | TokenType::RightBrace => {
+                // The value is missing. Leave the token for the surrounding
+                // array or object to resync on, if there is one.
+                self.error(unexpected(token))?;
+                let resyncs_container = match token.token_type {
+                    TokenType::Comma => !self.containers.is_empty(),
+                    _ => self.closes_open_container(token),
+                };
+                if !resyncs_container {
+                    self.tokens.next();
+                }
+                Ok(())
+            }
+            TokenType::Colon => {
+                self.tokens.next();
+                self.error(unexpected(token))
+            }
+        }
+    }
+
+    fn open_container(&mut self, opening: &Token, closing: TokenType<'static>) -> Result<(), Stop> {
+        if self.containers.len() >= self.limits.max_depth {
+            self.errors.push(ValidationError::DepthLimitExceeded(
+                opening.byte_offset,
+                self.limits.max_depth,
             ));
+            return Err(Stop);
+        }
+
+        // Special case: in an empty container we see the closing token
+        // immediately. in that case, consume it and early exit.
+        if self.peek()?.token_type == closing {
+            self.tokens.next();
+            return Ok(());
         }
-        let token = tokens.next_or_eof()?;
-        if !matches!(token.token_type, TokenType::Colon) {
-            return Err(ValidationError::UnexpectedToken(
+        let expect = match closing {
+            TokenType::RightBrace => Expect::Member,
+            _ => Expect::Item,
+        };
+        self.containers.push(Container {
+            closing,
+            expect,
+            members: 0,
+        });
+        Ok(())
+    }
+
+    /// Validates the key of an object member, then expects a colon.
+    fn validate_key(&mut self) -> Result<(), Stop> {
+        // Expect a string key, skipping over stray commas and brackets
+        loop {
+            let token = self.peek()?;
+            match token.token_type {
+                TokenType::String(_) => {
+                    self.tokens.next();
+                    break;
+                }
+                // We only get here after an error in this object, such as a
+                // stray token: no need to report the closing brace as well.
+                TokenType::RightBrace
+                    if self.containers.last().map(|container| &container.closing)
+                        == Some(&TokenType::RightBrace) =>
+                {
+                    self.expect(Expect::Separator);
+                    return Ok(());
+                }
+                TokenType::Comma | TokenType::RightBracket | TokenType::RightBrace => {
+                    self.error(unexpected(token))?;
+                    if self.closes_open_container(token) {
+                        self.expect(Expect::Separator);
+                        return Ok(());
+                    }
+                    self.tokens.next();
+                }
+                // The key is missing, but the value may still be there.
+                TokenType::Colon => {
+                    self.error(unexpected(token))?;
+                    break;
+                }
+                _ => {
+                    self.error(ValidationError::InvalidKey(
+                        token.byte_offset,
+                        token.to_string(),
+                    ))?;
+                    // Validate the key like a value, then expect the colon.
+                    self.expect(Expect::Colon);
+                    return self.validate_value();
+                }
+            }
+        }
+        self.expect(Expect::Colon);
+        Ok(())
+    }
+
+    /// Expects a colon followed by a value, then a separator.
+    fn validate_colon(&mut self) -> Result<(), Stop> {
+        self.expect(Expect::Separator);
+        let token = self.peek()?;
+        if matches!(token.token_type, TokenType::Colon) {
+            self.tokens.next();
+        } else {
+            self.error(ValidationError::MissingColon(
                 token.byte_offset,
                 token.to_string(),
-            ));
+            ))?;
+            if !starts_value(token) {
+                // The member has no value at all.
+                return Ok(());
+            }
         }
+        self.validate_value()
+    }
 
-        // Then expect a value, followed by a comma or a final closing brace
-        validate_recursive(tokens)?;
-        let token = tokens.next_or_eof()?;
-        match token.token_type {
-            TokenType::Comma => (),
-            TokenType::RightBrace => return Ok(()),
-            _ => {
-                return Err(ValidationError::UnexpectedToken(
-                    token.byte_offset,
-                    token.to_string(),
-                ))
+    /// Expects a comma or the closing token after an array item or object
+    /// member. Returns true once the container is closed.
+    fn validate_separator(&mut self, is_object: bool) -> Result<bool, Stop> {
+        let closing = if is_object {
+            TokenType::RightBrace
+        } else {
+            TokenType::RightBracket
+        };
+        loop {
+            let token = self.peek()?;
+            if token.token_type == closing {
+                self.tokens.next();
+                return Ok(true);
+            }
+            match token.token_type {
+                TokenType::Comma => {
+                    self.tokens.next();
+                    if self.peek()?.token_type == closing {
+                        self.error(ValidationError::TrailingComma(token.byte_offset))?;
+                        self.tokens.next();
+                        return Ok(true);
+                    }
+                    return Ok(false);
+                }
+                // Carry on as if the comma was there.
+                _ if starts_value(token) => {
+                    self.error(ValidationError::MissingComma(
+                        token.byte_offset,
+                        token.to_string(),
+                    ))?;
+                    return Ok(false);
+                }
+                _ => {
+                    self.error(unexpected(token))?;
+                    // A bracket closing an outer container means this one was
+                    // never closed: leave it for the outer one.
+                    if self.closes_open_container(token) {
+                        return Ok(true);
+                    }
+                    self.tokens.next();
+                }
             }
         }
     }
 }
+
+fn unexpected(token: &Token) -> ValidationError {
+    ValidationError::UnexpectedToken(token.byte_offset, token.to_string())
+}
@@ -0,0 +1,66 @@
+use formatjson::{
+    format_json, format_json_with_options, options::BlankLines, sort_keys::SortKeys,
+    tokenizer::tokenize, FormatOptions,
+};
+
+const SOURCE: &str = "{\r\n\r\n  \"b\": 1,\r\n\r\n\r\n  \"a\": [1, 2,\n\n 3]\n\n}";
+
+#[test]
+fn records_blank_lines_between_tokens() {
+    let tokens = tokenize(SOURCE, "<string>".to_string()).unwrap();
+    let blank_lines: Vec<_> = tokens
+        .iter()
+        .filter(|token| token.blank_line_before)
+        .map(|token| token.to_string())
+        .collect();
+    assert_eq!(blank_lines, ["\"b\"", "\"a\"", "3", "}"]);
+}
+
+#[test]
+fn keeps_one_blank_line_between_members() {
+    assert_eq!(
+        format_json(SOURCE).unwrap(),
+        "{\n  \"b\": 1,\n\n  \"a\": [\n    1,\n    2,\n\n    3\n  ]\n}\n"
+    );
+
+    let options = FormatOptions {
+        blank_lines: BlankLines::Remove,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format_json_with_options(SOURCE, &options).unwrap(),
+        "{\n  \"b\": 1,\n  \"a\": [\n    1,\n    2,\n    3\n  ]\n}\n"
+    );
+}
+
+#[test]
+fn counts_every_kind_of_line_break() {
+    for newline in ["\n", "\r\n", "\r"] {
+        let source = format!("[1,{0}{0}2,{0}3]", newline);
+        assert_eq!(
+            format_json(&source).unwrap(),
+            "[\n  1,\n\n  2,\n  3\n]\n",
+            "{:?}",
+            newline
+        );
+    }
+}
+
+#[test]
+fn keeps_blank_lines_before_a_comma_after_it() {
+    assert_eq!(format_json("[1\n\n, 2]").unwrap(), "[\n  1,\n\n  2\n]\n");
+}
+
+#[test]
+fn keeps_blank_lines_in_the_printer() {
+    // the blank lines move along with the members.
+    let options = FormatOptions {
+        max_line_width: Some(80),
+        sort_keys: Some(SortKeys::default()),
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format_json_with_options(SOURCE, &options).unwrap(),
+        "{\n  \"a\": [\n    1,\n    2,\n\n    3\n  ],\n\n  \"b\": 1\n}\n"
+    );
+}
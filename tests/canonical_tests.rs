@@ -0,0 +1,94 @@
+use formatjson::{canonical::es6_number, canonicalize, FormatJsonError};
+
+#[test]
+fn canonicalizes_the_rfc_example() {
+    let source = r#"{
+  "numbers": [333333333.33333329, 1E30, 4.50,
+              2e-3, 0.000000000000000000000000001],
+  "string": "€$\u000F\u000aA'B\"\\\\\"\/",
+  "literals": [null, true, false]
+}"#;
+    assert_eq!(
+        canonicalize(source).unwrap(),
+        r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+    );
+}
+
+#[test]
+fn sorts_keys_by_utf16_code_units() {
+    let source = r#"{
+  "€": "Euro Sign",
+  "\r": "Carriage Return",
+  "דּ": "Hebrew Letter Dalet With Dagesh",
+  "1": "One",
+  "😀": "Emoji: Grinning Face",
+  "\u0080": "Control",
+  "ö": "Latin Small Letter O With Diaeresis"
+}"#;
+    let keys: Vec<_> = canonicalize(source)
+        .unwrap()
+        .split(',')
+        .map(|member| member.split(':').nth(1).unwrap().to_string())
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            "\"Carriage Return\"",
+            "\"One\"",
+            "\"Control\"",
+            "\"Latin Small Letter O With Diaeresis\"",
+            "\"Euro Sign\"",
+            "\"Emoji",
+            "\"Hebrew Letter Dalet With Dagesh\"}",
+        ]
+    );
+}
+
+#[test]
+fn writes_numbers_like_ecmascript() {
+    let numbers = [
+        (0.0, "0"),
+        (-0.0, "0"),
+        (1e21, "1e+21"),
+        (1e20, "100000000000000000000"),
+        (0.000001, "0.000001"),
+        (1e-7, "1e-7"),
+        (-5e-324, "-5e-324"),
+        (1.7976931348623157e308, "1.7976931348623157e+308"),
+        (9007199254740994.0, "9007199254740994"),
+        (295147905179352830000.0, "295147905179352830000"),
+        (0.1 + 0.2, "0.30000000000000004"),
+    ];
+    for (number, expected) in numbers {
+        assert_eq!(es6_number(number), expected);
+    }
+}
+
+#[test]
+fn rejects_values_without_a_canonical_form() {
+    let Err(FormatJsonError::InvalidSyntax(error)) = canonicalize(r#"[1e400, "\ud800x", "😀"]"#)
+    else {
+        panic!("Expected an error");
+    };
+    let errors: Vec<_> = error.errors().map(|error| error.code()).collect();
+    assert_eq!(
+        errors,
+        [
+            "formatjson::number_out_of_range",
+            "formatjson::lone_surrogate"
+        ]
+    );
+
+    let Err(error) = canonicalize(r#"{"a": 1, "a": 2}"#) else {
+        panic!("Expected an error");
+    };
+    assert_eq!(error.code(), "formatjson::duplicate_key");
+}
+
+#[test]
+fn rejects_invalid_escapes() {
+    for source in [r#"["\x"]"#, r#"["\u12"]"#, r#"["\q"]"#] {
+        let error = canonicalize(source).unwrap_err();
+        assert_eq!(error.code(), "formatjson::invalid_escape", "{}", source);
+    }
+}
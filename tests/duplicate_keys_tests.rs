@@ -0,0 +1,99 @@
+use formatjson::{
+    duplicate_keys::{self, DuplicateKeyPolicy},
+    format_json_with_options, format_json_with_warnings, FormatOptions,
+};
+
+const SOURCE: &str = r#"{"a": 1, "b": {"c": 1, "c": 2}, "\u0061": 3}"#;
+
+fn options(duplicate_keys: DuplicateKeyPolicy) -> FormatOptions {
+    FormatOptions {
+        duplicate_keys,
+        ..FormatOptions::default()
+    }
+}
+
+#[test]
+fn warns_about_duplicate_keys() {
+    let formatted = format_json_with_warnings(SOURCE, &FormatOptions::default()).unwrap();
+    // the output keeps every member.
+    assert!(formatted.output.contains("\"\\u0061\": 3"));
+
+    let warnings: Vec<_> = formatted
+        .warnings
+        .iter()
+        .map(|warning| {
+            let related: Vec<_> = warning.related().collect();
+            (warning.message(), warning.span(), related)
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                "Duplicate key \"c\"",
+                23..26,
+                vec![(15..18, "First defined here")]
+            ),
+            (
+                "Duplicate key \"a\"",
+                32..40,
+                vec![(1..4, "First defined here")]
+            ),
+        ]
+    );
+    assert_eq!(formatted.warnings[0].code(), "formatjson::duplicate_key");
+    assert_eq!(formatted.warnings[0].severity(), miette::Severity::Warning);
+}
+
+#[test]
+fn fails_on_duplicate_keys() {
+    let error = format_json_with_options(SOURCE, &options(DuplicateKeyPolicy::Error)).unwrap_err();
+    let formatjson::FormatJsonError::InvalidSyntax(diagnostic) = error else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.errors().count(), 2);
+    assert_eq!(diagnostic.severity(), miette::Severity::Error);
+}
+
+#[test]
+fn keeps_first_or_last_member() {
+    let formatted =
+        format_json_with_warnings(SOURCE, &options(DuplicateKeyPolicy::KeepFirst)).unwrap();
+    assert_eq!(
+        formatted.output,
+        "{\n  \"a\": 1,\n  \"b\": {\n    \"c\": 1\n  }\n}\n"
+    );
+    assert_eq!(formatted.warnings.len(), 2);
+
+    let formatted =
+        format_json_with_warnings(SOURCE, &options(DuplicateKeyPolicy::KeepLast)).unwrap();
+    assert_eq!(
+        formatted.output,
+        "{\n  \"b\": {\n    \"c\": 2\n  },\n  \"\\u0061\": 3\n}\n"
+    );
+}
+
+#[test]
+fn allows_duplicate_keys() {
+    let formatted = format_json_with_warnings(SOURCE, &options(DuplicateKeyPolicy::Allow)).unwrap();
+    assert!(formatted.warnings.is_empty());
+}
+
+#[test]
+fn finds_the_same_duplicates_in_tokens_and_trees() {
+    let sources = [
+        SOURCE,
+        r#"[{"a": "a", "b": ["a", "a"]}, {"a": 1, "a": {"a": 2}}]"#,
+        r#"{"a": {}, "b": [{"a": 1}], "a": [], "b": "a"}"#,
+    ];
+    for source in sources {
+        let tokens = formatjson::tokenizer::tokenize(source, "x".to_string()).unwrap();
+        let mut tree = formatjson::parser::from_tokens(&tokens);
+        assert_eq!(
+            duplicate_keys::check_tokens(&tokens),
+            duplicate_keys::check(&mut tree, DuplicateKeyPolicy::Warn),
+            "{}",
+            source
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use formatjson::{format_json, FormatJsonError};
+use formatjson::{
+    format_json, format_json_with_options,
+    options::{BomPolicy, LineEnding},
+    FormatJsonError, FormatOptions,
+};
 
 #[test]
 fn test_json_example() -> Result<(), FormatJsonError> {
@@ -15,3 +19,38 @@ fn test_json_userdata() -> Result<(), FormatJsonError> {
     assert_eq!(format_json(input_json)?, expected_json);
     Ok(())
 }
+
+#[test]
+fn test_crlf_and_bom() -> Result<(), FormatJsonError> {
+    let input_json = "\u{FEFF}{\r\n\t\"foo\": [1,\r\n 2]\r\n}\r\n";
+    let mut options = FormatOptions::default();
+    assert_eq!(
+        format_json_with_options(input_json, &options)?,
+        "{\n  \"foo\": [\n    1,\n    2\n  ]\n}\n"
+    );
+
+    options.line_ending = LineEnding::Preserve;
+    options.bom = BomPolicy::Preserve;
+    assert_eq!(
+        format_json_with_options(input_json, &options)?,
+        "\u{FEFF}{\r\n  \"foo\": [\r\n    1,\r\n    2\r\n  ]\r\n}\r\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_line_ending_detection() {
+    assert_eq!(LineEnding::detect("[\r\n1]"), LineEnding::Crlf);
+    assert_eq!(LineEnding::detect("[\n1]\r\n"), LineEnding::Lf);
+    assert_eq!(LineEnding::detect("[1]"), LineEnding::Lf);
+}
+
+#[test]
+fn test_empty_containers() -> Result<(), FormatJsonError> {
+    assert_eq!(
+        format_json(r#"{"a": [ ], "b": {}}"#)?,
+        "{\n  \"a\": [],\n  \"b\": {}\n}\n"
+    );
+    assert_eq!(format_json("[]")?, "[]\n");
+    Ok(())
+}
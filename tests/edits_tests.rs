@@ -0,0 +1,56 @@
+use formatjson::{
+    edits::diff, format_edits, format_json_with_options, sort_keys::SortKeys, FormatOptions,
+    TextEdit,
+};
+
+#[test]
+fn only_edits_whitespace() {
+    let source = "{\"a\":[1,  2],\n  \"b\": \"x y\"}";
+    let edits = format_edits(source, "x.json", &FormatOptions::default()).unwrap();
+    for edit in &edits {
+        assert!(source[edit.range.clone()].trim().is_empty());
+        assert!(edit.replacement.trim().is_empty());
+    }
+    assert_eq!(
+        TextEdit::apply(&edits, source),
+        "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": \"x y\"\n}\n"
+    );
+}
+
+#[test]
+fn trims_edits_to_what_changed() {
+    let source = "{\n    \"a\": 1\n}\n";
+    assert_eq!(
+        format_edits(source, "x.json", &FormatOptions::default()).unwrap(),
+        [TextEdit {
+            range: 4..6,
+            replacement: String::new(),
+        }]
+    );
+    assert_eq!(
+        format_edits("[]\n", "x.json", &FormatOptions::default()).unwrap(),
+        []
+    );
+}
+
+#[test]
+fn replaces_changed_tokens_with_one_edit() {
+    let options = FormatOptions {
+        sort_keys: Some(SortKeys::default()),
+        ..FormatOptions::default()
+    };
+    let source = "{\"c\": 1, \"b\": 2, \"a\": [3]}";
+    let formatted = format_json_with_options(source, &options).unwrap();
+    let edits = format_edits(source, "x.json", &options).unwrap();
+    assert_eq!(TextEdit::apply(&edits, source), formatted);
+    // the members between the braces, then the final newline.
+    assert_eq!(edits.len(), 2);
+
+    assert_eq!(
+        diff("[1, 'a']", "[1, \"a\"]"),
+        [TextEdit {
+            range: 4..7,
+            replacement: "\"a\"".to_string(),
+        }]
+    );
+}
@@ -0,0 +1,70 @@
+use formatjson::{
+    encoding::{DecodedSource, Encoding, EncodingPolicy},
+    format_json_bytes, format_json_bytes_as_file,
+    options::BomPolicy,
+    FormatJsonError, FormatOptions,
+};
+
+#[test]
+fn detect_encoding() {
+    assert_eq!(Encoding::detect(b"{}"), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b"\xEF\xBB\xBF{}"), Encoding::Utf8);
+    assert_eq!(Encoding::detect(b"\xFF\xFE{\0}\0"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"\0{\0}"), Encoding::Utf16Be);
+    assert_eq!(Encoding::detect(b"1\0"), Encoding::Utf16Le);
+    assert_eq!(Encoding::detect(b"{\0\0\0}\0\0\0"), Encoding::Utf32Le);
+    assert_eq!(Encoding::detect(b"\0\0\0{\0\0\0}"), Encoding::Utf32Be);
+}
+
+#[test]
+fn format_utf16() -> Result<(), FormatJsonError> {
+    let source = "\u{FEFF}{\"key\": \"\u{1F600}\"}";
+    let utf16 = Encoding::Utf16Le.encode(source);
+    let mut options = FormatOptions::default();
+    assert_eq!(
+        format_json_bytes(&utf16, &options)?,
+        "{\n  \"key\": \"\u{1F600}\"\n}\n".as_bytes()
+    );
+
+    // the byte order mark is kept, even though the policy strips it.
+    options.encoding = EncodingPolicy::Preserve;
+    assert_eq!(options.bom, BomPolicy::Strip);
+    assert_eq!(
+        format_json_bytes(&utf16, &options)?,
+        Encoding::Utf16Le.encode("\u{FEFF}{\n  \"key\": \"\u{1F600}\"\n}\n")
+    );
+    Ok(())
+}
+
+#[test]
+fn error_offsets_in_original_bytes() {
+    let utf32 = Encoding::Utf32Be.encode("[1, 2,, 3]");
+    let FormatJsonError::InvalidSyntax(diagnostic) =
+        format_json_bytes(&utf32, &FormatOptions::default()).unwrap_err()
+    else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.byte_offset(), 6 * 4);
+    assert_eq!(diagnostic.filepath(), "<bytes>");
+
+    let error = format_json_bytes_as_file(&utf32, "<stdin>", &FormatOptions::default());
+    let Err(FormatJsonError::InvalidSyntax(diagnostic)) = error else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.filepath(), "<stdin>");
+
+    let decoded = DecodedSource::decode(&Encoding::Utf16Be.encode("\"\u{1F600}x\"")).unwrap();
+    assert_eq!(decoded.original_offset(1), 2);
+    assert_eq!(decoded.original_offset(5), 6);
+    assert_eq!(decoded.original_offset(7), 10);
+}
+
+#[test]
+fn invalid_utf16() {
+    // a lone high surrogate
+    let bytes = b"\"\0\x00\xD8\"\0";
+    assert!(matches!(
+        format_json_bytes(bytes, &FormatOptions::default()),
+        Err(FormatJsonError::InvalidEncoding(_))
+    ));
+}
@@ -0,0 +1,47 @@
+use formatjson::{error_format::ErrorFormat, format_json, FormatJsonError};
+
+fn syntax_error() -> FormatJsonError {
+    format_json("{\n  \"a\": 1,,\n}").unwrap_err()
+}
+
+#[test]
+fn json_error_format() {
+    let error = syntax_error();
+    let output = ErrorFormat::Json
+        .render(&[("config.json", &error)])
+        .unwrap();
+    assert!(output.starts_with("[\n  {\n    \"file\": \"config.json\",\n"));
+    assert!(output.contains("\"line\": 2,\n    \"column\": 10,"));
+    assert!(output.contains("\"span\": {\n      \"start\": 11,\n      \"end\": 12\n    }"));
+    // the output must itself be valid JSON.
+    format_json(&output).unwrap();
+}
+
+#[test]
+fn sarif_error_format() {
+    let error = syntax_error();
+    let output = ErrorFormat::Sarif
+        .render(&[("config.json", &error)])
+        .unwrap();
+    assert!(output.contains("\"version\": \"2.1.0\""));
+    assert!(output.contains("\"uri\": \"config.json\""));
+    assert!(output.contains("\"startLine\": 2,"));
+    format_json(&output).unwrap();
+}
+
+#[test]
+fn github_error_format() {
+    let error = syntax_error();
+    assert_eq!(
+        ErrorFormat::Github.render(&[("a,b.json", &error)]).unwrap(),
+        "::error file=a%2Cb.json,line=2,col=10,endLine=2,endColumn=11,\
+         title=formatjson%3A%3Aunexpected_token::Unexpected token: \",\"\n"
+    );
+
+    let error = FormatJsonError::FileNotFound("missing.json".to_string());
+    assert_eq!(
+        ErrorFormat::Github.render(&[("missing.json", &error)]).unwrap(),
+        "::error file=missing.json,title=formatjson%3A%3Afile_not_found::File missing.json not found\n"
+    );
+    assert_eq!(ErrorFormat::Fancy.render(&[("missing.json", &error)]), None);
+}
@@ -0,0 +1,64 @@
+use formatjson::{
+    format_json_with_options,
+    strings::{unquote, EscapeMode, HexCase, StringEscapes},
+    FormatOptions,
+};
+
+fn rewrite(literal: &str, mode: EscapeMode, hex_case: HexCase) -> String {
+    let escapes = StringEscapes {
+        mode,
+        hex_case,
+        unescape_slash: false,
+    };
+    let rewritten = escapes.rewrite(literal);
+    // the value of the string never changes.
+    assert_eq!(unquote(&rewritten), unquote(literal));
+    rewritten
+}
+
+/// Writes `%` as a backslash, to keep escapes readable.
+fn escaped(text: &str) -> String {
+    text.replace('%', "\\")
+}
+
+#[test]
+fn unescapes_needless_escapes() {
+    let literal = escaped(r#""caf%u00e9 %ud83d%ude00 %" %%u0041 %u000a %ud800 %n%/""#);
+    assert_eq!(
+        rewrite(&literal, EscapeMode::Minimal, HexCase::Preserve),
+        escaped("\"caf\u{e9} \u{1f600} %\" %%u0041 %u000a %ud800 %n%/\"")
+    );
+    assert_eq!(
+        rewrite(&literal, EscapeMode::Preserve, HexCase::Upper),
+        escaped(r#""caf%u00E9 %uD83D%uDE00 %" %%u0041 %u000A %uD800 %n%/""#)
+    );
+}
+
+#[test]
+fn escapes_everything_outside_ascii() {
+    let literal = escaped("\"caf\u{e9} \u{1f600} %u00e9%u0041\"");
+    assert_eq!(
+        rewrite(&literal, EscapeMode::Ascii, HexCase::Preserve),
+        escaped(r#""caf%u00e9 %ud83d%ude00 %u00e9A""#)
+    );
+    assert_eq!(
+        rewrite(&literal, EscapeMode::Ascii, HexCase::Upper),
+        escaped(r#""caf%u00E9 %uD83D%uDE00 %u00E9A""#)
+    );
+}
+
+#[test]
+fn rewrites_keys_and_values() {
+    let options = FormatOptions {
+        string_escapes: StringEscapes {
+            mode: EscapeMode::Minimal,
+            unescape_slash: true,
+            ..StringEscapes::default()
+        },
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format_json_with_options(&escaped(r#"{"%u00e9": ["a%/b"]}"#), &options).unwrap(),
+        "{\n  \"\u{e9}\": [\n    \"a/b\"\n  ]\n}\n"
+    );
+}
@@ -0,0 +1,87 @@
+use formatjson::{infer_schema::infer_schema, parser, schema::Schema};
+
+fn parse_all(sources: &[&str]) -> Vec<formatjson::nodes::JSONFile> {
+    sources
+        .iter()
+        .map(|source| parser::parse(source, "<source>").unwrap())
+        .collect()
+}
+
+#[test]
+fn infers_types_and_required_keys() {
+    let documents = parse_all(&[
+        r#"{"name": "a", "size": 1, "tags": ["x", 2]}"#,
+        r#"{"name": "b", "size": 1.5, "extra": null}"#,
+    ]);
+    assert_eq!(
+        infer_schema(&documents),
+        r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "type": "object",
+  "properties": {
+    "name": {
+      "type": "string"
+    },
+    "size": {
+      "type": "number"
+    },
+    "tags": {
+      "type": "array",
+      "items": {
+        "type": [
+          "string",
+          "integer"
+        ]
+      }
+    },
+    "extra": {
+      "type": "null"
+    }
+  },
+  "required": [
+    "name",
+    "size"
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn infers_enums_for_repeated_values() {
+    let documents = parse_all(&[r#"[{"level": "info", "n": 1}, {"level": "warn", "n": 2}]"#]);
+    let schema = infer_schema(&documents);
+    // each number appears once, so it's not an enum.
+    assert!(!schema.contains("\"enum\""));
+
+    let documents = parse_all(&[
+        r#"[{"level": "info"}, {"level": "warn"}, {"level": null}]"#,
+        r#"[{"level": "info"}]"#,
+    ]);
+    let schema = infer_schema(&documents);
+    assert!(schema.contains(
+        r#"
+        "type": [
+          "string",
+          "null"
+        ],
+        "enum": [
+          "info",
+          "warn",
+          null
+        ]"#
+    ));
+}
+
+#[test]
+fn samples_match_the_inferred_schema() {
+    let sources = [
+        include_str!("./json_data/userdata.json"),
+        include_str!("./json_data/simple.json"),
+    ];
+    for source in sources {
+        let documents = parse_all(&[source]);
+        let schema = Schema::from_source(&infer_schema(&documents), None).unwrap();
+        assert!(schema.validate(&documents[0]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use formatjson::{
+    format_json_bytes, format_json_with_options,
+    limits::Limits,
+    tokenizer::tokenize,
+    validator::{validate_with_limits, ValidationError},
+    FormatJsonError, FormatOptions,
+};
+
+fn options(limits: Limits) -> FormatOptions {
+    FormatOptions {
+        limits,
+        ..FormatOptions::default()
+    }
+}
+
+#[test]
+fn deep_nesting_does_not_overflow() {
+    let source = "[".repeat(100_000);
+    let error = format_json_with_options(&source, &FormatOptions::default()).unwrap_err();
+    assert_eq!(error.code(), "formatjson::limit_exceeded");
+    assert_eq!(error.to_string(), "Nesting is deeper than the limit of 512");
+
+    // without a depth limit, the validator still doesn't recurse.
+    let tokens = tokenize(&source, "<source>".into()).unwrap();
+    let limits = Limits {
+        max_depth: usize::MAX,
+        ..Limits::default()
+    };
+    let errors = validate_with_limits(&tokens, 20, &limits);
+    assert!(matches!(errors[..], [ValidationError::UnexpectedEOF(_)]));
+}
+
+#[test]
+fn depth_limit() {
+    let limits = Limits {
+        max_depth: 2,
+        ..Limits::default()
+    };
+    assert!(format_json_with_options("[[1], {\"a\": 1}]", &options(limits)).is_ok());
+    let error = format_json_with_options("[[1], {\"a\": []}]", &options(limits)).unwrap_err();
+    let FormatJsonError::InvalidSyntax(diagnostic) = error else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.span(), 12..13);
+}
+
+#[test]
+fn size_limit() {
+    let limits = Limits {
+        max_size: 8,
+        ..Limits::default()
+    };
+    assert!(format_json_with_options("[1, 2]", &options(limits)).is_ok());
+    let error = format_json_with_options("[1, 2, 3]", &options(limits)).unwrap_err();
+    assert_eq!(error.code(), "formatjson::limit_exceeded");
+    let FormatJsonError::InvalidSyntax(diagnostic) = error else {
+        panic!("Expected a syntax error");
+    };
+    // the text past the limit.
+    assert_eq!(diagnostic.span(), 8..9);
+
+    // bytes are checked before they are decoded.
+    let error = format_json_bytes(b"[1, 2, 3]", &options(limits)).unwrap_err();
+    assert_eq!(error.code(), "formatjson::limit_exceeded");
+}
+
+#[test]
+fn string_length_limit() {
+    let limits = Limits {
+        max_string_length: 3,
+        ..Limits::default()
+    };
+    assert!(format_json_with_options(r#"{"abc": "def"}"#, &options(limits)).is_ok());
+    let error = format_json_with_options(r#"{"abc": "defg"}"#, &options(limits)).unwrap_err();
+    let FormatJsonError::InvalidSyntax(diagnostic) = error else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.span(), 8..14);
+}
+
+#[test]
+fn members_limit() {
+    let limits = Limits {
+        max_members: 2,
+        ..Limits::default()
+    };
+    assert!(format_json_with_options(r#"{"a": [1, 2], "b": {}}"#, &options(limits)).is_ok());
+    let error = format_json_with_options("[1, [2], 3]", &options(limits)).unwrap_err();
+    let FormatJsonError::InvalidSyntax(diagnostic) = error else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.span(), 9..10);
+    assert!(format_json_with_options(r#"{"a": 1, "b": 2, "c": 3}"#, &options(limits)).is_err());
+}
@@ -0,0 +1,143 @@
+use formatjson::{
+    lsp::{read_message, run, uri_to_path, write_message, Server},
+    sort_keys::KeyOrderRule,
+    strings::quote,
+    FormatOptions,
+};
+
+fn open(server: &mut Server, text: &str) -> String {
+    let message = format!(
+        r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument": {{"uri": "file:///a.json", "languageId": "json", "version": 1, "text": {}}}}}}}"#,
+        quote(text)
+    );
+    server.handle(&message).remove(0)
+}
+
+fn request(server: &mut Server, method: &str, params: &str) -> String {
+    let message = format!(
+        r#"{{"jsonrpc": "2.0", "id": 7, "method": "{}", "params": {{"textDocument": {{"uri": "file:///a.json"}}{}}}}}"#,
+        method, params
+    );
+    server.handle(&message).remove(0)
+}
+
+#[test]
+fn publishes_diagnostics_for_open_documents() {
+    let mut server = Server::new(FormatOptions::default());
+    let published = open(&mut server, "{\"a\": 1,}");
+    assert!(published.contains(r#""method": "textDocument/publishDiagnostics""#));
+    assert!(published.contains(
+        r#"{"range": {"start": {"line": 0, "character": 7}, "end": {"line": 0, "character": 8}}, "severity": 1, "code": "formatjson::trailing_comma""#
+    ));
+
+    let published = open(&mut server, "{\"a\": 1}");
+    assert!(published.ends_with(r#""diagnostics": []}}"#));
+}
+
+#[test]
+fn formats_documents_and_ranges() {
+    let mut server = Server::new(FormatOptions::default());
+    open(&mut server, "{\"a\":[1,2]}\n");
+    assert_eq!(
+        request(&mut server, "textDocument/formatting", ""),
+        r#"{"jsonrpc": "2.0", "id": 7, "result": [{"range": {"start": {"line": 0, "character": 1}, "end": {"line": 0, "character": 1}}, "newText": "\n  "}, {"range": {"start": {"line": 0, "character": 5}, "end": {"line": 0, "character": 5}}, "newText": " "}, {"range": {"start": {"line": 0, "character": 6}, "end": {"line": 0, "character": 6}}, "newText": "\n    "}, {"range": {"start": {"line": 0, "character": 8}, "end": {"line": 0, "character": 8}}, "newText": "\n    "}, {"range": {"start": {"line": 0, "character": 9}, "end": {"line": 0, "character": 9}}, "newText": "\n  "}, {"range": {"start": {"line": 0, "character": 10}, "end": {"line": 0, "character": 10}}, "newText": "\n"}]}"#
+    );
+    assert_eq!(
+        request(
+            &mut server,
+            "textDocument/rangeFormatting",
+            r#", "range": {"start": {"line": 0, "character": 8}, "end": {"line": 0, "character": 9}}"#
+        ),
+        r#"{"jsonrpc": "2.0", "id": 7, "result": [{"range": {"start": {"line": 0, "character": 8}, "end": {"line": 0, "character": 8}}, "newText": "\n    "}]}"#
+    );
+}
+
+#[test]
+fn lists_symbols_and_folding_ranges() {
+    let mut server = Server::new(FormatOptions::default());
+    open(
+        &mut server,
+        "{\n  \"a\": {\n    \"b\": [\n      1\n    ]\n  }\n}\n",
+    );
+    let symbols = request(&mut server, "textDocument/documentSymbol", "");
+    assert!(symbols.contains(r#""name": "a", "kind": 19"#));
+    assert!(symbols.contains(r#""name": "b", "kind": 18"#));
+    assert_eq!(
+        request(&mut server, "textDocument/foldingRange", ""),
+        r#"{"jsonrpc": "2.0", "id": 7, "result": [{"startLine": 0, "endLine": 5}, {"startLine": 1, "endLine": 4}, {"startLine": 2, "endLine": 3}]}"#
+    );
+}
+
+#[test]
+fn shows_the_json_pointer_on_hover() {
+    let mut server = Server::new(FormatOptions::default());
+    open(&mut server, r#"{"a/b": [{"~": true}]}"#);
+    let hover = request(
+        &mut server,
+        "textDocument/hover",
+        r#", "position": {"line": 0, "character": 16}"#,
+    );
+    assert!(hover.contains(r#""value": "`/a~1b/0/~0`""#));
+}
+
+#[test]
+fn serves_messages_until_exit() {
+    let mut input = vec![];
+    for message in [
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#,
+        r#"{"jsonrpc": "2.0", "id": "two", "method": "unknown"}"#,
+        // a response to the server, which gets no reply.
+        r#"{"jsonrpc": "2.0", "id": 9, "result": null}"#,
+        r#"{"jsonrpc": "2.0", "id": null, "method": "unknown"}"#,
+        r#"{"jsonrpc": "2.0", "id": 3, "method": "shutdown"}"#,
+        r#"{"jsonrpc": "2.0", "method": "exit"}"#,
+        r#"{"jsonrpc": "2.0", "id": 4, "method": "shutdown"}"#,
+    ] {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = vec![];
+    run(input.as_slice(), &mut output, &FormatOptions::default()).unwrap();
+
+    let mut output = output.as_slice();
+    let mut replies = vec![];
+    while let Some(reply) = read_message(&mut output).unwrap() {
+        replies.push(reply);
+    }
+    assert_eq!(replies.len(), 4);
+    assert!(replies[0].contains(r#""documentFormattingProvider": true"#));
+    assert!(replies[1].contains(r#""id": "two", "error": {"code": -32601"#));
+    assert!(replies[2].contains(r#""id": null, "error": {"code": -32601"#));
+    assert_eq!(replies[3], r#"{"jsonrpc": "2.0", "id": 3, "result": null}"#);
+}
+
+#[test]
+fn fails_on_messages_shorter_than_their_length() {
+    let mut input = "Content-Length: 1000000000\r\n\r\n{}".as_bytes();
+    let error = read_message(&mut input).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn reads_paths_from_uris() {
+    assert_eq!(
+        uri_to_path("file:///home/me/a%20b.json"),
+        "/home/me/a b.json"
+    );
+    assert_eq!(uri_to_path("file:///c%3A/Users/a.json"), "c:/Users/a.json");
+    assert_eq!(uri_to_path("untitled:Untitled-1"), "untitled:Untitled-1");
+}
+
+#[test]
+fn formats_documents_as_their_file() {
+    let options = FormatOptions {
+        key_order: vec![KeyOrderRule::new("a.json", ["b"])],
+        ..FormatOptions::default()
+    };
+    let mut server = Server::new(options);
+    open(&mut server, "{\n  \"a\": 1,\n  \"b\": 2\n}\n");
+    // the key order rule for a.json moves "b" first.
+    assert_eq!(
+        request(&mut server, "textDocument/formatting", ""),
+        r#"{"jsonrpc": "2.0", "id": 7, "result": [{"range": {"start": {"line": 1, "character": 3}, "end": {"line": 2, "character": 8}}, "newText": "b\": 2,\n  \"a\": 1"}]}"#
+    );
+}
@@ -0,0 +1,81 @@
+use formatjson::{
+    format_json_with_warnings,
+    numbers::{NumberFormat, NumberNotation},
+    FormatOptions,
+};
+
+#[test]
+fn tidies_numbers_in_their_notation() {
+    let format = NumberFormat {
+        lowercase_exponent: true,
+        strip_exponent_plus: true,
+        strip_trailing_zeros: true,
+        ..NumberFormat::default()
+    };
+    let numbers = [
+        ("1.50E+3", "1.5e3"),
+        ("-2.000e-04", "-2e-04"),
+        ("1.0", "1"),
+        ("100", "100"),
+        ("0.0", "0"),
+    ];
+    for (raw, expected) in numbers {
+        assert_eq!(format.rewrite(raw), expected);
+    }
+}
+
+#[test]
+fn converts_between_notations() {
+    let plain = NumberFormat {
+        notation: NumberNotation::Plain,
+        ..NumberFormat::default()
+    };
+    let scientific = NumberFormat {
+        notation: NumberNotation::Scientific,
+        ..NumberFormat::default()
+    };
+    let numbers = [
+        ("1.50E+3", "1500", "1.5e3"),
+        ("0.00150", "0.0015", "1.5e-3"),
+        ("-12e-1", "-1.2", "-1.2"),
+        ("1e-7", "1e-7", "1e-7"),
+        ("1e22", "1e22", "1e22"),
+        (
+            "123456789012345678901",
+            "123456789012345678901",
+            "1.23456789012345678901e20",
+        ),
+        ("-0.0", "-0", "-0"),
+    ];
+    for (raw, expected_plain, expected_scientific) in numbers {
+        assert_eq!(plain.rewrite(raw), expected_plain);
+        assert_eq!(scientific.rewrite(raw), expected_scientific);
+    }
+}
+
+#[test]
+fn warns_about_numbers_parsers_cant_read_exactly() {
+    let options = FormatOptions {
+        numbers: NumberFormat {
+            warnings: true,
+            ..NumberFormat::default()
+        },
+        ..FormatOptions::default()
+    };
+    let source = "[0.1, 9007199254740991, -9007199254740992, 1.5e300, 2.00000000000000001, 1e999]";
+    let formatted = format_json_with_warnings(source, &options).unwrap();
+    let warnings: Vec<_> = formatted
+        .warnings
+        .iter()
+        .map(|warning| (warning.code(), warning.span()))
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            ("formatjson::unsafe_integer", 24..41),
+            ("formatjson::unsafe_integer", 43..50),
+            ("formatjson::inexact_number", 52..71),
+            ("formatjson::number_out_of_range", 73..78),
+        ]
+    );
+}
@@ -0,0 +1,45 @@
+use formatjson::{nodes::JSONNode, parser, strings::unquote, tokenizer};
+
+#[test]
+fn parses_values() {
+    let file = parser::parse(r#"{"a": [1.50, true, null], "b\n": "\u00e9"}"#, "x").unwrap();
+    let JSONNode::Object(object) = &file.value else {
+        panic!("Expected an object");
+    };
+    assert_eq!(object.span, 0..42);
+    assert_eq!(object.members.len(), 2);
+    assert_eq!(object.members[1].key.value, "b\n");
+    assert_eq!(object.members[1].key.raw, "\"b\\n\"");
+
+    let JSONNode::Array(array) = &object.members[0].value else {
+        panic!("Expected an array");
+    };
+    assert_eq!(array.span, 6..24);
+    let JSONNode::Number(number) = &array.items[0] else {
+        panic!("Expected a number");
+    };
+    assert_eq!((number.value, number.raw.as_str()), (1.5, "1.50"));
+    let JSONNode::String(string) = &object.members[1].value else {
+        panic!("Expected a string");
+    };
+    assert_eq!(string.value, "é");
+}
+
+#[test]
+fn turns_back_into_tokens() {
+    let source = r#"{"a": [1, {"b": []}], "c": {}}"#;
+    let tokens = tokenizer::tokenize(source, "x".to_string()).unwrap();
+    let file = parser::from_tokens(&tokens);
+    let to_strings =
+        |tokens: &[tokenizer::Token]| tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    assert_eq!(to_strings(&file.tokens()), to_strings(&tokens));
+    assert_eq!(file.tokens()[1].span(), tokens[1].span());
+}
+
+#[test]
+fn unquotes_strings() {
+    assert_eq!(unquote(r#""a\"b\\c\/d""#), "a\"b\\c/d");
+    assert_eq!(unquote(r#""\ud83d\ude00 \u0041""#), "😀 A");
+    // lone surrogates can't be decoded.
+    assert_eq!(unquote(r#""\ud83d!""#), "\u{FFFD}!");
+}
@@ -0,0 +1,184 @@
+use formatjson::{format_json_with_options, options::LineEnding, FormatOptions};
+
+fn format(source: &str, max_line_width: usize) -> String {
+    let options = FormatOptions {
+        max_line_width: Some(max_line_width),
+        ..FormatOptions::default()
+    };
+    format_json_with_options(source, &options).unwrap()
+}
+
+#[test]
+fn keeps_containers_that_fit_inline() {
+    let source = r#"{"point": [1, 2, 3], "empty": [], "nested": {"a": {}, "b": [true, null]}}"#;
+    assert_eq!(
+        format(source, 40),
+        r#"{
+  "point": [1, 2, 3],
+  "empty": [],
+  "nested": {"a": {}, "b": [true, null]}
+}
+"#
+    );
+    assert_eq!(format(source, 80), format!("{}\n", source));
+}
+
+#[test]
+fn expands_containers_that_dont_fit() {
+    let source = r#"[["a long string", "another one"], [1, 2]]"#;
+    assert_eq!(
+        format(source, 20),
+        r#"[
+  [
+    "a long string",
+    "another one"
+  ],
+  [1, 2]
+]
+"#
+    );
+}
+
+#[test]
+fn counts_the_trailing_comma() {
+    // `[1, 2],` is 9 characters wide with the indent, `[1, 2]` is 8.
+    assert_eq!(
+        format("[[1, 2], [3]]", 8),
+        "[\n  [\n    1,\n    2\n  ],\n  [3]\n]\n"
+    );
+    assert_eq!(format("[[1, 2], [3]]", 9), "[\n  [1, 2],\n  [3]\n]\n");
+}
+
+#[test]
+fn uses_the_line_ending() {
+    let options = FormatOptions {
+        max_line_width: Some(10),
+        line_ending: LineEnding::Crlf,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format_json_with_options(r#"{"a": [1, 2, 3]}"#, &options).unwrap(),
+        "{\r\n  \"a\": [\r\n    1,\r\n    2,\r\n    3\r\n  ]\r\n}\r\n"
+    );
+}
+
+fn format_tabular(source: &str, max_line_width: Option<usize>) -> String {
+    let options = FormatOptions {
+        tabular: true,
+        max_line_width,
+        ..FormatOptions::default()
+    };
+    format_json_with_options(source, &options).unwrap()
+}
+
+#[test]
+fn aligns_arrays_of_similar_objects_as_tables() {
+    let source = r#"[
+        {"id": 1, "name": "x", "tags": ["a"]},
+        {"id": 10, "name": "abc", "tags": []}
+    ]"#;
+    assert_eq!(
+        format_tabular(source, None),
+        r#"[
+  {"id": 1,  "name": "x",   "tags": ["a"]},
+  {"id": 10, "name": "abc", "tags": []}
+]
+"#
+    );
+}
+
+#[test]
+fn falls_back_when_not_a_table() {
+    // the keys differ, so the objects are formatted as usual.
+    let source = r#"[{"a": 1}, {"b": 2}]"#;
+    assert_eq!(
+        format_tabular(source, None),
+        "[\n  {\n    \"a\": 1\n  },\n  {\n    \"b\": 2\n  }\n]\n"
+    );
+    // the rows are 19 characters wide with the indent and comma, so only the
+    // last one fits once the array is laid out as usual.
+    let source = r#"[{"a": 1, "b": 2}, {"a": 3, "b": 4}]"#;
+    assert_eq!(
+        format_tabular(source, Some(19)),
+        "[\n  {\"a\": 1, \"b\": 2},\n  {\"a\": 3, \"b\": 4}\n]\n"
+    );
+    assert_eq!(
+        format_tabular(source, Some(18)),
+        "[\n  {\n    \"a\": 1,\n    \"b\": 2\n  },\n  {\"a\": 3, \"b\": 4}\n]\n"
+    );
+}
+
+#[test]
+fn aligns_object_values() {
+    let options = FormatOptions {
+        align_values: Some(4),
+        ..FormatOptions::default()
+    };
+    let source = r#"{"a": 1, "bbb": {"x": 1, "yy": [2]}, "a_very_long_key": 3, "": []}"#;
+    assert_eq!(
+        format_json_with_options(source, &options).unwrap(),
+        r#"{
+  "a":   1,
+  "bbb": {
+    "x":  1,
+    "yy": [
+      2
+    ]
+  },
+  "a_very_long_key": 3,
+  "":    []
+}
+"#
+    );
+}
+
+#[test]
+fn fills_arrays_of_scalars() {
+    let options = FormatOptions {
+        fill_arrays: true,
+        max_line_width: Some(20),
+        ..FormatOptions::default()
+    };
+    let source = r#"{"values": [1, 22, 333, 4444, 55555, null, "six", true], "mixed": [1, [2]]}"#;
+    assert_eq!(
+        format_json_with_options(source, &options).unwrap(),
+        r#"{
+  "values": [
+    1, 22, 333,
+    4444, 55555,
+    null, "six",
+    true
+  ],
+  "mixed": [1, [2]]
+}
+"#
+    );
+}
+
+#[test]
+fn writes_matrices_one_row_per_line() {
+    let options = FormatOptions {
+        matrix_rows: true,
+        ..FormatOptions::default()
+    };
+    let source = r#"{"matrix": [[1, 0, 0], [0, 1, 0], [0, 0, 1]], "other": [[1], ["a"]]}"#;
+    assert_eq!(
+        format_json_with_options(source, &options).unwrap(),
+        r#"{
+  "matrix": [
+    [1, 0, 0],
+    [0, 1, 0],
+    [0, 0, 1]
+  ],
+  "other": [
+    [
+      1
+    ],
+    [
+      "a"
+    ]
+  ]
+}
+"#
+    );
+}
@@ -0,0 +1,52 @@
+use formatjson::{format_json_with_options, profile::IJson, FormatJsonError, FormatOptions};
+
+fn errors(source: &str, profile: IJson) -> Vec<(&'static str, std::ops::Range<usize>)> {
+    let options = FormatOptions {
+        profile: Some(profile),
+        ..FormatOptions::default()
+    };
+    match format_json_with_options(source, &options) {
+        Ok(_) => vec![],
+        Err(FormatJsonError::InvalidSyntax(error)) => error
+            .errors()
+            .map(|error| (error.code(), error.span()))
+            .collect(),
+        Err(error) => panic!("Unexpected error {:?}", error),
+    }
+}
+
+#[test]
+fn accepts_i_json() {
+    let source = r#"{"name": "café 😀", "ids": [1, -2.5, 9007199254740991, 1.5e-300]}"#;
+    assert_eq!(errors(source, IJson::default()), []);
+    assert_eq!(errors("42", IJson::default()), []);
+}
+
+#[test]
+fn reports_every_violation_in_source_order() {
+    let source = "{\"a\": 1e999, \"b\": \"x\\ud800\", \"a\": 0.30000000000000000001, \"\u{FFFF}\": [\"\u{1FFFE}\", -9007199254740992]}";
+    assert_eq!(
+        errors(source, IJson::default()),
+        [
+            ("formatjson::number_out_of_range", 6..11),
+            ("formatjson::lone_surrogate", 18..27),
+            ("formatjson::duplicate_key", 29..32),
+            ("formatjson::inexact_number", 34..56),
+            ("formatjson::noncharacter", 58..63),
+            ("formatjson::noncharacter", 66..72),
+            ("formatjson::unsafe_integer", 74..91),
+        ]
+    );
+}
+
+#[test]
+fn requires_a_container_when_asked_to() {
+    let profile = IJson {
+        require_container: true,
+    };
+    assert_eq!(
+        errors("\"text\"", profile),
+        [("formatjson::scalar_top_level", 0..6)]
+    );
+    assert_eq!(errors("[\"text\"]", profile), []);
+}
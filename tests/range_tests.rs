@@ -0,0 +1,66 @@
+use formatjson::{format_range, FormatOptions, TextEdit};
+
+/// Formats the range between the `|` markers, and returns the result.
+fn format_selection(marked: &str) -> String {
+    let start = marked.find('|').unwrap();
+    let end = marked.rfind('|').unwrap() - 1;
+    let source = marked.replace('|', "");
+    let edits = format_range(&source, start..end, &FormatOptions::default()).unwrap();
+    TextEdit::apply(&edits, &source)
+}
+
+#[test]
+fn formats_the_enclosing_value() {
+    let source = "{\"a\": [1,2],\n \"b\": {\"c\":|tr|ue,   \"d\":[3]}}";
+    assert_eq!(
+        format_selection(source),
+        "{\"a\": [1,2],\n \"b\": {\"c\": true,   \"d\":[3]}}"
+    );
+}
+
+#[test]
+fn formats_the_selected_members_at_their_depth() {
+    let source = "{\"a\": [1,2],\n \"b\": {\"c\":|true,   \"d\":|[3], \"e\": null}}";
+    assert_eq!(
+        format_selection(source),
+        "{\"a\": [1,2],\n \"b\": {\n    \"c\": true,\n    \"d\": [\n      3\n    ], \"e\": null}}"
+    );
+}
+
+#[test]
+fn formats_the_whole_container_when_touching_its_brackets() {
+    let source = "{\"a\": [1,2],\n \"b\": |{\"c\":true}|}";
+    assert_eq!(
+        format_selection(source),
+        "{\"a\": [1,2],\n \"b\": {\n    \"c\": true\n  }}"
+    );
+    assert_eq!(format_selection("  |[1,{}]|  "), "  [\n  1,\n  {}\n]  ");
+}
+
+#[test]
+fn returns_no_edits_when_already_formatted() {
+    let source = "{\n  \"a\": [\n    1\n  ]\n}\n";
+    assert_eq!(
+        format_range(source, 10..11, &FormatOptions::default()).unwrap(),
+        []
+    );
+}
+
+#[test]
+fn trims_the_edit_to_what_changed() {
+    let source = "{\n  \"a\": [1,2]\n}\n";
+    assert_eq!(
+        format_range(source, 12..13, &FormatOptions::default()).unwrap(),
+        [TextEdit {
+            range: 12..12,
+            replacement: "\n    ".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn formats_the_file_for_a_cursor_past_its_end() {
+    let source = "[1,2]";
+    let edits = format_range(source, 10..10, &FormatOptions::default()).unwrap();
+    assert_eq!(TextEdit::apply(&edits, source), "[\n  1,\n  2\n]");
+}
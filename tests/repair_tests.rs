@@ -0,0 +1,123 @@
+use formatjson::{
+    error_format::ErrorFormat, errors::ErrorCode, format_json_with_options, repair::repair,
+    repair_json, FormatOptions,
+};
+
+#[test]
+fn repairs_lexical_mistakes() {
+    let repaired = repair("{'a': True, b: None, 'c': 'say \"hi\"'} // done", "x").unwrap();
+    assert_eq!(
+        repaired.source,
+        "{\"a\": true, \"b\": null, \"c\": \"say \\\"hi\\\"\"} "
+    );
+    let codes: Vec<_> = repaired.fixes.iter().map(|fix| fix.code).collect();
+    assert_eq!(
+        codes,
+        vec![
+            ErrorCode::SingleQuotedString,
+            ErrorCode::PythonLiteral,
+            ErrorCode::UnquotedKey,
+            ErrorCode::PythonLiteral,
+            ErrorCode::SingleQuotedString,
+            ErrorCode::SingleQuotedString,
+            ErrorCode::TrailingText,
+        ]
+    );
+}
+
+#[test]
+fn repairs_structural_mistakes() {
+    let cases = [
+        ("[1 2,]", "[1, 2]"),
+        ("{\"a\" 1, \"b\": }", "{\"a\": 1, \"b\": null }"),
+        ("{\"a\"}", "{\"a\": null}"),
+        ("{\"a\": [1, {\"b\": 2", "{\"a\": [1, {\"b\": 2}]}"),
+        ("{\"a\": 1,", "{\"a\": 1}"),
+        ("{\"a\":", "{\"a\":null}"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(repair(source, "x").unwrap().source, expected, "{}", source);
+    }
+}
+
+#[test]
+fn fails_on_unrepairable_errors() {
+    // two commas in a row can't be repaired with any certainty.
+    let error = repair("[1,, 2]", "x").unwrap_err();
+    assert_eq!(error.code(), "formatjson::unexpected_token");
+}
+
+#[test]
+fn leaves_valid_json_alone() {
+    let repaired = repair("{\"a\": [1, 2]}", "x").unwrap();
+    assert_eq!(repaired.source, "{\"a\": [1, 2]}");
+    assert!(repaired.fixes.is_empty());
+}
+
+#[test]
+fn replaces_whitespace_the_tokenizer_rejects() {
+    let repaired = repair("{\"a\":\u{a0}1,\u{2028}\"b\": 2}", "x").unwrap();
+    assert_eq!(repaired.source, "{\"a\": 1, \"b\": 2}");
+    let spans: Vec<_> = repaired.fixes.iter().map(|fix| fix.span.clone()).collect();
+    assert_eq!(spans, vec![5..7, 9..12]);
+    assert!(repaired
+        .fixes
+        .iter()
+        .all(|fix| fix.code == ErrorCode::NonAsciiWhitespace));
+}
+
+#[test]
+fn only_repairs_when_asked_to() {
+    let options = FormatOptions::default();
+    assert!(format_json_with_options("{'a': 1}", &options).is_err());
+
+    let options = FormatOptions {
+        repair: true,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format_json_with_options("{'a': 1}", &options).unwrap(),
+        "{\n  \"a\": 1\n}\n"
+    );
+}
+
+#[test]
+fn warnings_point_at_the_original_source() {
+    let formatted = repair_json("{a: 1 'b': 2,}").unwrap();
+    assert_eq!(formatted.output, "{\n  \"a\": 1,\n  \"b\": 2\n}\n");
+
+    let warnings: Vec<_> = formatted
+        .warnings
+        .iter()
+        .map(|warning| (warning.code(), warning.span()))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            ("formatjson::unquoted_key", 1..2),
+            ("formatjson::missing_comma", 5..5),
+            ("formatjson::single_quoted_string", 6..9),
+            ("formatjson::trailing_comma", 12..13),
+        ]
+    );
+    for warning in &formatted.warnings {
+        assert_eq!(warning.severity(), miette::Severity::Warning);
+    }
+}
+
+#[test]
+fn renders_warnings() {
+    let formatted = repair_json("[1, 2,]").unwrap();
+    let warnings: Vec<_> = formatted
+        .warnings
+        .iter()
+        .map(|warning| ("a.json", warning))
+        .collect();
+    assert_eq!(
+        ErrorFormat::Github.render_diagnostics(&warnings).unwrap(),
+        "::warning file=a.json,line=1,col=6,endLine=1,endColumn=7,\
+         title=formatjson%3A%3Atrailing_comma::Removed trailing comma\n"
+    );
+    let output = ErrorFormat::Json.render_diagnostics(&warnings).unwrap();
+    assert!(output.contains("\"severity\": \"warning\""));
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use formatjson::{
+    format_json_with_options, parser,
+    schema::{Schema, SchemaSource},
+    FormatJsonError, FormatOptions,
+};
+
+const SCHEMA_DIR: &str = "tests/json_data/schema";
+
+fn schema_errors(schema: &str, document: &str) -> Vec<(String, String)> {
+    let schema = Schema::from_source(schema, None).unwrap();
+    let document = parser::parse(document, "<source>").unwrap();
+    schema
+        .validate(&document)
+        .unwrap()
+        .into_iter()
+        .map(|error| (error.path, error.message))
+        .collect()
+}
+
+#[test]
+fn validates_keywords() {
+    let schema = r#"{
+        "type": "object",
+        "required": ["a", "b"],
+        "properties": {
+            "a": {"type": ["integer", "null"], "exclusiveMinimum": 0},
+            "c": {"const": {"x": [1, 2]}},
+            "d": {"type": "string", "maxLength": 2}
+        },
+        "patternProperties": {"^e": {"type": "boolean"}},
+        "additionalProperties": {"type": "string"}
+    }"#;
+    let document = r#"{"a": 0, "c": {"x": [2, 1]}, "d": "ééé", "e1": 1, "f": 2}"#;
+    assert_eq!(
+        schema_errors(schema, document),
+        vec![
+            ("".into(), "Missing required property \"b\"".into()),
+            ("/a".into(), "Expected a number > 0".into()),
+            ("/c".into(), "Expected {\"x\": [1, 2]}".into()),
+            ("/d".into(), "Expected at most 2 characters".into()),
+            ("/e1".into(), "Expected boolean, found number".into()),
+            ("/f".into(), "Expected string, found number".into()),
+        ]
+    );
+    assert!(schema_errors(schema, r#"{"a": null, "b": "x", "c": {"x": [1, 2.0]}}"#).is_empty());
+}
+
+#[test]
+fn array_items_by_draft() {
+    let draft_2020 = r#"{"prefixItems": [{"type": "string"}], "items": {"type": "number"}}"#;
+    assert_eq!(
+        schema_errors(draft_2020, r#"["a", 1, "b"]"#),
+        vec![("/2".into(), "Expected number, found string".into())]
+    );
+
+    let draft_07 = r#"{
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "items": [{"type": "string"}],
+        "additionalItems": false
+    }"#;
+    assert_eq!(
+        schema_errors(draft_07, r#"["a", 1]"#),
+        vec![("/1".into(), "No value is allowed here".into())]
+    );
+}
+
+#[test]
+fn broken_schemas() {
+    let document = parser::parse("[\"a\"]", "<source>").unwrap();
+    for schema in [
+        r##"{"$ref": "#/$defs/missing"}"##,
+        r##"{"$defs": {"a": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}"##,
+        r#"{"items": {"pattern": "("}}"#,
+    ] {
+        let schema = Schema::from_source(schema, None).unwrap();
+        let error = schema.validate(&document).unwrap_err();
+        assert!(
+            matches!(error, FormatJsonError::InvalidSchema(_)),
+            "{}",
+            error
+        );
+    }
+}
+
+#[test]
+fn follows_recursive_refs_into_deep_documents() {
+    let schema = r##"{"type": "object", "properties": {"child": {"$ref": "#"}}}"##;
+    let depth = 100;
+    let document = format!("{}{{}}{}", "{\"child\": ".repeat(depth), "}".repeat(depth));
+    assert!(schema_errors(schema, &document).is_empty());
+
+    // a cycle is still found below the top-level value.
+    let schema = r##"{"$defs": {"a": {"$ref": "#/$defs/a"}}, "items": {"$ref": "#/$defs/a"}}"##;
+    let schema = Schema::from_source(schema, None).unwrap();
+    let document = parser::parse("[[1]]", "<source>").unwrap();
+    let error = schema.validate(&document).unwrap_err();
+    assert!(
+        matches!(&error, FormatJsonError::InvalidSchema(message) if message.contains("Too many nested $refs")),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn validates_while_formatting() {
+    let options = FormatOptions {
+        schema: SchemaSource::File(Path::new(SCHEMA_DIR).join("config.schema.json")),
+        ..FormatOptions::default()
+    };
+    let valid = include_str!("./json_data/schema/config.json");
+    assert!(format_json_with_options(valid, &options).is_ok());
+
+    let invalid = include_str!("./json_data/schema/invalid_config.json");
+    let FormatJsonError::InvalidSyntax(diagnostic) =
+        format_json_with_options(invalid, &options).unwrap_err()
+    else {
+        panic!("Expected schema errors");
+    };
+    let errors: Vec<_> = diagnostic
+        .errors()
+        .map(|error| (error.line_col().to_string(), error.message()))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            (
+                "3:11".into(),
+                "Expected to match the pattern \"^[a-z]+$\" at /name"
+            ),
+            ("4:11".into(), "Expected integer, found number at /port"),
+            ("4:11".into(), "Expected a number <= 65535 at /port"),
+            ("5:11".into(), "Expected at most 2 items at /tags"),
+            ("5:17".into(), "Expected one of \"a\", \"b\" at /tags/1"),
+            (
+                "6:22".into(),
+                "Expected string, found number at /server/host"
+            ),
+            ("7:3".into(), "Property \"extra\" is not allowed"),
+        ]
+    );
+    assert_eq!(diagnostic.code(), "formatjson::schema_violation");
+}
+
+#[test]
+fn schema_key() {
+    let options = FormatOptions {
+        schema: SchemaSource::Document,
+        ..FormatOptions::default()
+    };
+    let path = format!("{}/invalid_config.json", SCHEMA_DIR);
+    let document = parser::parse(&std::fs::read_to_string(&path).unwrap(), &path).unwrap();
+    let schema = options.schema.load(&document, &path).unwrap().unwrap();
+    assert_eq!(schema.validate(&document).unwrap().len(), 7);
+
+    // remote schemas are not fetched.
+    let document = parser::parse(r#"{"$schema": "https://example.com/s.json"}"#, "x").unwrap();
+    assert!(options.schema.load(&document, "x").unwrap().is_none());
+}
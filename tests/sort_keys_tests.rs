@@ -0,0 +1,154 @@
+use formatjson::{
+    format_json_file_with_options, format_json_with_options,
+    sort_keys::{KeyOrder, KeyOrderRule, SortKeys},
+    FormatOptions,
+};
+
+fn sort(source: &str, order: KeyOrder, depths: Option<Vec<usize>>) -> String {
+    let options = FormatOptions {
+        sort_keys: Some(SortKeys { order, depths }),
+        ..FormatOptions::default()
+    };
+    format_json_with_options(source, &options).unwrap()
+}
+
+#[test]
+fn sorts_keys_recursively() {
+    let source = r#"{"b": [{"z": 1, "y": 2}], "a": {"d": null, "c": true}}"#;
+    assert_eq!(
+        sort(source, KeyOrder::Lexicographic, None),
+        r#"{
+  "a": {
+    "c": true,
+    "d": null
+  },
+  "b": [
+    {
+      "y": 2,
+      "z": 1
+    }
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn sorts_keys_at_given_depths() {
+    let source = r#"{"b": {"d": 1, "c": 2}, "a": [{"f": 1, "e": 2}]}"#;
+    assert_eq!(
+        sort(source, KeyOrder::Lexicographic, Some(vec![2])),
+        r#"{
+  "b": {
+    "d": 1,
+    "c": 2
+  },
+  "a": [
+    {
+      "e": 2,
+      "f": 1
+    }
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn compares_keys() {
+    let keys = ["item10", "B", "item2", "a", "item02", "A"];
+    let sorted = |order: KeyOrder| {
+        let mut keys = keys.to_vec();
+        keys.sort_by(|a, b| order.compare(a, b));
+        keys
+    };
+    assert_eq!(
+        sorted(KeyOrder::Lexicographic),
+        ["A", "B", "a", "item02", "item10", "item2"]
+    );
+    assert_eq!(
+        sorted(KeyOrder::Natural),
+        ["A", "B", "a", "item02", "item2", "item10"]
+    );
+    assert_eq!(
+        sorted(KeyOrder::CaseInsensitive),
+        ["A", "a", "B", "item02", "item10", "item2"]
+    );
+}
+
+#[test]
+fn keeps_the_order_of_duplicate_keys() {
+    let source = r#"{"b": 1, "a": 2, "b": 3}"#;
+    let options = FormatOptions {
+        duplicate_keys: formatjson::duplicate_keys::DuplicateKeyPolicy::Allow,
+        sort_keys: Some(SortKeys::default()),
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        format_json_with_options(source, &options).unwrap(),
+        "{\n  \"a\": 2,\n  \"b\": 1,\n  \"b\": 3\n}\n"
+    );
+}
+
+#[test]
+fn matches_key_order_globs() {
+    let rule = |glob: &str| KeyOrderRule::new(glob, []);
+    assert!(rule("package.json").matches("packages/app/package.json"));
+    assert!(!rule("package.json").matches("package.json.bak"));
+    assert!(rule("tsconfig*.json").matches("tsconfig.build.json"));
+    assert!(rule("*.schema.json").matches("C:\\schemas\\config.schema.json"));
+    assert!(rule("config/**/*.json").matches("config/a/b/c.json"));
+    assert!(rule("config/**/*.json").matches("config/c.json"));
+    assert!(!rule("config/*.json").matches("config/a/c.json"));
+    assert!(!rule("**/config.json").matches("myconfig.json"));
+}
+
+#[test]
+fn puts_preset_keys_first() {
+    let path = std::env::temp_dir().join("formatjson_key_order/package.json");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(
+        &path,
+        r#"{"scripts": {"test": "jest"}, "zebra": 1, "version": "1.0.0", "apple": 2, "name": "app"}"#,
+    )
+    .unwrap();
+
+    let path = path.to_str().unwrap();
+    let options = FormatOptions {
+        key_order: KeyOrderRule::presets(),
+        ..FormatOptions::default()
+    };
+    format_json_file_with_options(path, &options).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path).unwrap(),
+        r#"{
+  "name": "app",
+  "version": "1.0.0",
+  "scripts": {
+    "test": "jest"
+  },
+  "zebra": 1,
+  "apple": 2
+}
+"#
+    );
+
+    // the remaining keys are sorted, if asked to.
+    let options = FormatOptions {
+        sort_keys: Some(SortKeys::default()),
+        ..options
+    };
+    format_json_file_with_options(path, &options).unwrap();
+    assert!(std::fs::read_to_string(path)
+        .unwrap()
+        .ends_with("  \"apple\": 2,\n  \"zebra\": 1\n}\n"));
+}
+
+#[test]
+fn parses_key_order_rules() {
+    assert_eq!(
+        "*.json=b, a".parse(),
+        Ok(KeyOrderRule::new("*.json", ["b", "a"]))
+    );
+    assert!("*.json".parse::<KeyOrderRule>().is_err());
+}
@@ -1,6 +1,8 @@
 use formatjson::{
+    format_json,
+    line_index::LineIndex,
     tokenizer::tokenize,
-    validator::{validate, ValidationError},
+    validator::{validate, validate_all, ValidationError},
     FormatJsonError,
 };
 
@@ -66,3 +68,204 @@ fn valid_json_empty() {
     let tokens = tokenize(unclosed_array, "<source>".into()).unwrap();
     validate(&tokens).expect("Expected validate to pass");
 }
+
+#[test]
+fn token_spans() {
+    let source = "{\"é\": [true, 12.5]}";
+    let tokens = tokenize(source, "<source>".into()).unwrap();
+    let spans: Vec<_> = tokens.iter().map(|token| token.span()).collect();
+    assert_eq!(
+        spans,
+        vec![
+            0..1,
+            1..5,
+            5..6,
+            7..8,
+            8..12,
+            12..13,
+            14..18,
+            18..19,
+            19..20
+        ]
+    );
+}
+
+#[test]
+fn line_and_column() {
+    let source = "{\r\n  \"😀\": [1,\n    ]\n}";
+    let index = LineIndex::new(source);
+    let position = index.line_col(source.find(']').unwrap());
+    assert_eq!(position.line, 2);
+    assert_eq!(position.column, 4);
+    assert_eq!(position.to_string(), "3:5");
+
+    let position = index.line_col(source.find(':').unwrap());
+    assert_eq!(
+        (position.line, position.column, position.utf16_column),
+        (1, 5, 6)
+    );
+}
+
+#[test]
+fn lines_end_with_cr_crlf_or_lf() {
+    let source = "[1,\r2,\r\n3,\n4]";
+    let index = LineIndex::new(source);
+    let lines: Vec<_> = ['1', '2', '3', '4']
+        .iter()
+        .map(|&digit| index.line_col(source.find(digit).unwrap()).to_string())
+        .collect();
+    assert_eq!(lines, ["1:2", "2:1", "3:1", "4:1"]);
+
+    // columns past the end stop before the whole line ending.
+    assert_eq!(index.offset(0, 10), 3);
+    assert_eq!(index.offset(1, 10), 6);
+    assert_eq!(index.offset(2, 10), 10);
+    assert_eq!(index.offset(3, 10), source.len());
+}
+
+#[test]
+fn error_location_after_a_cr() {
+    let source = "{\"a\":1\r\"b\":2}";
+    let FormatJsonError::InvalidSyntax(diagnostic) = format_json(source).unwrap_err() else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.line_col().to_string(), "2:1");
+}
+
+#[test]
+fn rejects_invalid_escapes() {
+    let cases = [
+        (r#""\x""#, 1..3),
+        (r#""\u12""#, 1..5),
+        (r#""\u12g4""#, 1..5),
+        (r#""\q""#, 1..3),
+        (r#""\é""#, 1..4),
+    ];
+    for (source, span) in cases {
+        let Err(FormatJsonError::InvalidSyntax(diagnostic)) = tokenize(source, "x".into()) else {
+            panic!("Expected a syntax error for {}", source);
+        };
+        assert_eq!(
+            diagnostic.code(),
+            "formatjson::invalid_escape",
+            "{}",
+            source
+        );
+        assert_eq!(diagnostic.span(), span, "{}", source);
+    }
+    assert!(tokenize(r#""\" \\ \/ \b \f \n \r \t \u00aF""#, "x".into()).is_ok());
+}
+
+#[test]
+fn error_location() {
+    let source = "[\n  1,\n  2,,\n]";
+    let FormatJsonError::InvalidSyntax(diagnostic) = format_json(source).unwrap_err() else {
+        panic!("Expected a syntax error");
+    };
+    assert_eq!(diagnostic.line_col().to_string(), "3:5");
+    assert_eq!(diagnostic.span(), 11..12);
+    assert_eq!(diagnostic.filepath(), "<string>");
+}
+
+#[test]
+fn specific_syntax_errors() {
+    let validate_source = |source| validate(&tokenize(source, "<source>".into()).unwrap());
+    assert!(matches!(
+        validate_source("[1, 2,]").unwrap_err(),
+        ValidationError::TrailingComma(5)
+    ));
+    assert!(matches!(
+        validate_source(r#"{"a": 1,}"#).unwrap_err(),
+        ValidationError::TrailingComma(7)
+    ));
+    assert!(matches!(
+        validate_source("[1 2]").unwrap_err(),
+        ValidationError::MissingComma(3, _)
+    ));
+    assert!(matches!(
+        validate_source(r#"{"a" 1}"#).unwrap_err(),
+        ValidationError::MissingColon(5, _)
+    ));
+    assert!(matches!(
+        validate_source("{1: 2}").unwrap_err(),
+        ValidationError::InvalidKey(1, _)
+    ));
+}
+
+#[test]
+fn error_codes_and_help() {
+    let error = |source| match format_json(source).unwrap_err() {
+        FormatJsonError::InvalidSyntax(diagnostic) => diagnostic,
+        error => panic!("Expected a syntax error, got {:?}", error),
+    };
+    assert_eq!(error("[1,]").code(), "formatjson::trailing_comma");
+    assert_eq!(error("\"abc").code(), "formatjson::unterminated_string");
+    assert_eq!(error("[01]").code(), "formatjson::invalid_number");
+    assert_eq!(error("[True]").code(), "formatjson::unexpected_character");
+    assert_eq!(error("[nul]").code(), "formatjson::invalid_literal");
+    assert_eq!(error("").code(), "formatjson::empty_file");
+    assert!(error("[1,]").help().contains("trailing comma"));
+
+    // an unexpected EOF also points at the bracket that was never closed.
+    let diagnostic = error(r#"[{"a": [1]}, {"b": 2"#);
+    assert_eq!(diagnostic.code(), "formatjson::unexpected_eof");
+    assert_eq!(diagnostic.span(), 20..20);
+    let related: Vec<_> = diagnostic.related().collect();
+    assert_eq!(related, vec![(13..14, "Unclosed bracket")]);
+}
+
+#[test]
+fn numbers() {
+    for number in ["0", "-0", "1e+5", "2.5E-3", "10", "-12.75e10"] {
+        let tokens = tokenize(number, "<source>".into()).unwrap();
+        validate(&tokens).expect("Expected validate to pass");
+    }
+    for number in ["01", "1.", ".5", "-", "1e", "+1", "1.2.3", "1-2"] {
+        assert!(tokenize(number, "<source>".into()).is_err(), "{}", number);
+    }
+}
+
+#[test]
+fn recovers_from_errors() {
+    let source = r#"{"a": [1 2,], "b" 3, "c": {]}, "d": 1"#;
+    let tokens = tokenize(source, "<source>".into()).unwrap();
+    let errors = validate_all(&tokens, 20);
+    let codes: Vec<_> = errors.iter().map(|error| error.code().as_str()).collect();
+    assert_eq!(
+        codes,
+        vec![
+            "formatjson::missing_comma",
+            "formatjson::trailing_comma",
+            "formatjson::missing_colon",
+            "formatjson::unexpected_token",
+            "formatjson::unexpected_eof",
+        ]
+    );
+    assert_eq!(validate_all(&tokens, 2).len(), 2);
+
+    // A bracket closing an outer container ends the inner one too.
+    let tokens = tokenize(r#"{"a": [1, 2}"#, "<source>".into()).unwrap();
+    let errors = validate_all(&tokens, 20);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], ValidationError::UnexpectedToken(11, _)));
+
+    let tokens = tokenize("[1,,2]", "<source>".into()).unwrap();
+    assert_eq!(validate_all(&tokens, 20).len(), 1);
+}
+
+#[test]
+fn reports_all_errors() {
+    let FormatJsonError::InvalidSyntax(diagnostic) = format_json("[1,, 2 3,]").unwrap_err() else {
+        panic!("Expected a syntax error");
+    };
+    let spans: Vec<_> = diagnostic.errors().map(|error| error.span()).collect();
+    assert_eq!(spans, vec![3..4, 7..8, 8..9]);
+}
+
+#[test]
+fn reports_eof_after_the_last_token() {
+    let tokens = tokenize("[1,", "<source>".into()).unwrap();
+    let errors = validate_all(&tokens, 20);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span(), 3..3);
+}